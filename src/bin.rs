@@ -1,12 +1,46 @@
-use roborockutil::{discovery, deviceinfo, provisioning};
+use roborockutil::{discovery, deviceinfo, provisioning, history, zone, room, dnd, fileserver, ota, map, render};
+use roborockutil::history::ExportFormat;
+use roborockutil::zone::Zone;
+use roborockutil::dnd::DndWindow;
+use roborockutil::timer::{CleanTimer, Weekdays};
+use roborockutil::sound::{SoundInstallState, SoundInstallTracker};
+use roborockutil::fileserver::FileServer;
+use roborockutil::ota::{OtaState, OtaTracker};
+use roborockutil::map::RRMap;
+use roborockutil::render::{ImageFormat, Layers, RenderOptions, Rotation};
+use roborockutil::restriction::Restrictions;
+use roborockutil::carpet::CarpetCleanMode;
+use roborockutil::device::Device;
+use roborockutil::miiopayloads::{StatusResponse, ControlCommand, ConsumableStatus, CleanRecord, SoundInstall,
+                                  SoundProgress};
+use roborockutil::consumable::Consumable;
+use roborockutil::fanspeed::{FanSpeed, FanSpeedScheme};
 use miiobin::{MI_DISCOVER_UDP_PORT};
 extern crate clap;
-use clap::{Arg, App, SubCommand, ArgMatches};
+use clap::{Arg, App, AppSettings, SubCommand, ArgMatches};
 use std::net::{Ipv4Addr, UdpSocket};
 use std::str::{FromStr, from_utf8};
 use std::process;
 use std::error::Error as StdError;
 use std::fmt;
+use std::path::Path;
+use std::fs;
+use std::io::{self, BufRead};
+use std::thread;
+use std::time::Duration;
+use serde_json::Value;
+
+/// Duration of each movement of the interactive `drive` mode, in milliseconds
+const DRIVE_MOVE_MILLIS: u64 = 1000;
+
+/// How often the progress of a voice pack installation is polled, in milliseconds
+const INSTALL_POLL_MILLIS: u64 = 2000;
+
+/// How long to wait for a voice pack installation to end, in seconds
+const SOUND_INSTALL_TIMEOUT_SECS: u64 = 600;
+
+/// How long to wait for a firmware update to end, in seconds
+const OTA_TIMEOUT_SECS: u64 = 1800;
 
 #[derive(Debug)]
 enum ArgError {
@@ -18,183 +52,1653 @@ fn main() {
     let arg_cmd_name_discover = "discover";
     let arg_cmd_name_status = "status";
     let arg_cmd_name_info = "info";
+    let arg_cmd_name_command = "command";
+    let arg_cmd_name_provision = "provision";
+    let arg_cmd_name_fanspeed = "fanspeed";
+    let arg_cmd_name_consumables = "consumables";
+    let arg_cmd_name_history = "history";
+    let arg_cmd_name_zoned = "zoned";
+    let arg_cmd_name_goto = "goto";
+    let arg_cmd_name_drive = "drive";
+    let arg_cmd_name_rooms = "rooms";
+    let arg_cmd_name_dnd = "dnd";
+    let arg_cmd_name_timers = "timers";
+    let arg_cmd_name_sound = "sound";
+    let arg_cmd_name_ota = "ota";
+    let arg_cmd_name_map = "map";
+    let arg_cmd_name_restrictions = "restrictions";
+    let arg_cmd_name_maps = "maps";
+    let arg_cmd_name_carpet = "carpet";
+    let control_cmds = [
+        ("start", "Start (or resume) cleaning", ControlCommand::Start),
+        ("pause", "Pause cleaning", ControlCommand::Pause),
+        ("stop", "Stop cleaning", ControlCommand::Stop),
+        ("home", "Return to the charging dock", ControlCommand::Home),
+        ("spot", "Clean the area around the current position", ControlCommand::Spot),
+        ("locate", "Play a sound, to help find the robot", ControlCommand::Locate),
+    ];
+
+    let arg_name_sip = "sip";
+    let sip_arg = Arg::with_name(arg_name_sip)
+        .long(arg_name_sip)
+        .help("The local IP on which to open the UDP socket")
+        .takes_value(true);
+
+    let arg_name_dip= "dip";
+    let dip_arg = Arg::with_name(arg_name_dip)
+        .long(arg_name_dip)
+        .help("The IP of the target device.")
+        .takes_value(true);
+
+    let arg_name_token = "token";
+    let token_arg = Arg::with_name(arg_name_token)
+        .long(arg_name_token)
+        .help("Token used for encryption/decryption (16 alphanumeric characters)")
+        .takes_value(true);
+
+    let arg_name_method = "method";
+    let method_arg = Arg::with_name(arg_name_method)
+        .long(arg_name_method)
+        .help("Name of the miio method, e.g. get_status")
+        .takes_value(true);
+
+    let arg_name_params = "params";
+    let params_arg = Arg::with_name(arg_name_params)
+        .long(arg_name_params)
+        .help("Method parameters, as a JSON array or object")
+        .takes_value(true)
+        .default_value("[]");
+
+    let arg_name_ssid = "ssid";
+    let ssid_arg = Arg::with_name(arg_name_ssid)
+        .long(arg_name_ssid)
+        .help("Name of the wifi network to which the device should connect")
+        .takes_value(true);
+
+    let arg_name_passwd = "passwd";
+    let passwd_arg = Arg::with_name(arg_name_passwd)
+        .long(arg_name_passwd)
+        .help("Password of the wifi network to which the device should connect")
+        .takes_value(true);
+
+    let arg_name_uid = "uid";
+    let uid_arg = Arg::with_name(arg_name_uid)
+        .long(arg_name_uid)
+        .help("User account ID to which the device is bound")
+        .takes_value(true);
+
+    let arg_name_country = "country";
+    let country_arg = Arg::with_name(arg_name_country)
+        .long(arg_name_country)
+        .help("Cloud server region, e.g. de, us, cn")
+        .takes_value(true);
+
+    let arg_name_tz = "tz";
+    let tz_arg = Arg::with_name(arg_name_tz)
+        .long(arg_name_tz)
+        .help("Time zone name, e.g. Europe/Berlin")
+        .takes_value(true);
+
+    let arg_name_set = "set";
+    let fanspeed_set_arg = Arg::with_name(arg_name_set)
+        .long(arg_name_set)
+        .help("Suction power to set: quiet, balanced, turbo, max, mop, or a numeric value")
+        .takes_value(true);
+
+    let arg_name_scheme = "scheme";
+    let scheme_arg = Arg::with_name(arg_name_scheme)
+        .long(arg_name_scheme)
+        .help("Numeric suction power scheme of the firmware: percentage, s5 or code. Detected if not given.")
+        .takes_value(true)
+        .possible_values(&["percentage", "s5", "code"]);
+
+    let arg_name_reset = "reset";
+    let reset_arg = Arg::with_name(arg_name_reset)
+        .long(arg_name_reset)
+        .help("Consumable to reset after replacing it")
+        .takes_value(true)
+        .possible_values(&["main_brush", "side_brush", "filter", "sensor", "filter_element", "strainer",
+                           "cleaning_brush", "dust_collection"]);
+
+    let arg_name_limit = "limit";
+    let limit_arg = Arg::with_name(arg_name_limit)
+        .long(arg_name_limit)
+        .help("Maximum number of clean records to show")
+        .takes_value(true)
+        .default_value("10");
+
+    let arg_cmd_name_export = "export";
+
+    let arg_name_format = "format";
+    let format_arg = Arg::with_name(arg_name_format)
+        .long(arg_name_format)
+        .help("Format of the output file: csv, jsonl, or sqlite (if built with the sqlite feature)")
+        .takes_value(true)
+        .default_value("csv");
+
+    let arg_name_output = "output";
+    let output_arg = Arg::with_name(arg_name_output)
+        .long(arg_name_output)
+        .help("Output file. New records are appended to it.")
+        .takes_value(true);
+
+    let arg_name_zone = "zone";
+    let zone_arg = Arg::with_name(arg_name_zone)
+        .long(arg_name_zone)
+        .help("Zone to clean, as x1,y1,x2,y2[,repeats] in robot coordinates (mm). Can be given multiple times.")
+        .takes_value(true)
+        .multiple(true)
+        .number_of_values(1);
+
+    let arg_name_zones_file = "zones-file";
+    let zones_file_arg = Arg::with_name(arg_name_zones_file)
+        .long(arg_name_zones_file)
+        .help("JSON file with named zones, e.g. {\"kitchen\": [{\"x1\": 25000, \"y1\": 22000, \"x2\": 28500, \"y2\": 25000}]}")
+        .takes_value(true);
+
+    let arg_name_name = "name";
+    let name_arg = Arg::with_name(arg_name_name)
+        .long(arg_name_name)
+        .help("Name of the zones to clean, from the named zones file")
+        .takes_value(true);
+
+    let arg_name_x = "x";
+    let x_arg = Arg::with_name(arg_name_x)
+        .long(arg_name_x)
+        .help("X coordinate of the target, in robot coordinates (mm)")
+        .takes_value(true);
+
+    let arg_name_y = "y";
+    let y_arg = Arg::with_name(arg_name_y)
+        .long(arg_name_y)
+        .help("Y coordinate of the target, in robot coordinates (mm)")
+        .takes_value(true);
+
+    let arg_name_velocity = "velocity";
+    let velocity_arg = Arg::with_name(arg_name_velocity)
+        .long(arg_name_velocity)
+        .help("Velocity when driving forward or backward, in m/s (at most 0.3)")
+        .takes_value(true)
+        .default_value("0.2");
+
+    let arg_name_rotation = "rotation";
+    let rotation_arg = Arg::with_name(arg_name_rotation)
+        .long(arg_name_rotation)
+        .help("Rotation when turning left or right, in degrees (at most 179)")
+        .takes_value(true)
+        .default_value("45");
+
+    let arg_name_names_file = "names-file";
+    let names_file_arg = Arg::with_name(arg_name_names_file)
+        .long(arg_name_names_file)
+        .help("JSON file with room names and their segment ids, e.g. {\"Kitchen\": 16, \"Living room\": 17}")
+        .takes_value(true);
+
+    let arg_name_clean = "clean";
+    let clean_arg = Arg::with_name(arg_name_clean)
+        .long(arg_name_clean)
+        .help("Name of a room to clean. Can be given multiple times.")
+        .takes_value(true)
+        .multiple(true)
+        .number_of_values(1);
+
+    let arg_name_repeats = "repeats";
+    let repeats_arg = Arg::with_name(arg_name_repeats)
+        .long(arg_name_repeats)
+        .help("How many times to clean each room")
+        .takes_value(true)
+        .default_value("1");
+
+    let arg_name_window = "window";
+    let window_arg = Arg::with_name(arg_name_window)
+        .long(arg_name_window)
+        .help("Quiet hours to set, as HH:MM-HH:MM (e.g. 22:00-08:00)")
+        .takes_value(true);
+
+    let arg_name_disable = "disable";
+    let disable_arg = Arg::with_name(arg_name_disable)
+        .long(arg_name_disable)
+        .help("Disable the quiet hours")
+        .conflicts_with(arg_name_window);
+
+    let arg_cmd_name_add = "add";
+    let arg_cmd_name_enable = "enable";
+    let arg_cmd_name_disable = "disable";
+    let arg_cmd_name_delete = "delete";
+
+    let arg_name_time = "time";
+    let time_arg = Arg::with_name(arg_name_time)
+        .long(arg_name_time)
+        .help("Time of day at which to start cleaning, as HH:MM")
+        .takes_value(true);
+
+    let arg_name_days = "days";
+    let days_arg = Arg::with_name(arg_name_days)
+        .long(arg_name_days)
+        .help("Days on which to clean: daily, workdays, weekend, or a list of days (e.g. mon,wed,fri)")
+        .takes_value(true)
+        .default_value("daily");
+
+    let arg_name_fanspeed = "fanspeed";
+    let timer_fanspeed_arg = Arg::with_name(arg_name_fanspeed)
+        .long(arg_name_fanspeed)
+        .help("Suction power to clean with: quiet, balanced, turbo, max, mop, or a numeric value")
+        .takes_value(true);
+
+    let arg_name_segments = "segments";
+    let segments_arg = Arg::with_name(arg_name_segments)
+        .long(arg_name_segments)
+        .help("Segment (room) ids to clean, e.g. 16,17. The whole map is cleaned if not given.")
+        .takes_value(true);
+
+    let arg_name_id = "id";
+    let timer_id_arg = Arg::with_name(arg_name_id)
+        .long(arg_name_id)
+        .help("Id of the timer, as listed by the timers subcommand")
+        .takes_value(true);
+
+    let arg_cmd_name_install = "install";
+
+    let arg_name_volume = "volume";
+    let volume_arg = Arg::with_name(arg_name_volume)
+        .long(arg_name_volume)
+        .help("Sound volume to set, between 0 and 100")
+        .takes_value(true);
+
+    let arg_name_test = "test";
+    let test_arg = Arg::with_name(arg_name_test)
+        .long(arg_name_test)
+        .help("Play a test sound at the current volume");
+
+    let arg_name_url = "url";
+    let url_arg = Arg::with_name(arg_name_url)
+        .long(arg_name_url)
+        .help("URL from which the robot downloads the package. It must be reachable by the robot.")
+        .takes_value(true);
+
+    let arg_name_file = "file";
+    let file_arg = Arg::with_name(arg_name_file)
+        .long(arg_name_file)
+        .help("Local package file. It is served to the robot over HTTP, on the local IP given with --sip.")
+        .takes_value(true)
+        .conflicts_with(arg_name_url);
+
+    let arg_name_md5 = "md5";
+    let md5_arg = Arg::with_name(arg_name_md5)
+        .long(arg_name_md5)
+        .help("MD5 checksum of the package. Computed from the file if --file is given.")
+        .takes_value(true);
+
+    let arg_name_port = "port";
+    let port_arg = Arg::with_name(arg_name_port)
+        .long(arg_name_port)
+        .help("TCP port of the local HTTP server, when --file is given. Any free port is used if not given.")
+        .takes_value(true)
+        .default_value("0");
+
+    let arg_name_sid = "sid";
+    let sid_arg = Arg::with_name(arg_name_sid)
+        .long(arg_name_sid)
+        .help("Id of the voice pack")
+        .takes_value(true);
+
+    let arg_name_min_battery = "min-battery";
+    let min_battery_arg = Arg::with_name(arg_name_min_battery)
+        .long(arg_name_min_battery)
+        .help("Lowest battery level (in percent) at which the update is started")
+        .takes_value(true);
+
+    let arg_name_input = "input";
+    let input_arg = Arg::with_name(arg_name_input)
+        .long(arg_name_input)
+        .help("Map file in the RRMap format (gzipped or not), e.g. as downloaded from the map URL")
+        .takes_value(true);
+
+    let arg_cmd_name_render = "render";
+
+    let image_output_arg = Arg::with_name(arg_name_output)
+        .long(arg_name_output)
+        .help("Image file. It is overwritten if it exists.")
+        .takes_value(true);
+
+    let image_format_arg = Arg::with_name(arg_name_format)
+        .long(arg_name_format)
+        .help("Format of the image: png or svg. Guessed from the output file name if not given.")
+        .takes_value(true)
+        .possible_values(&["png", "svg"]);
+
+    let arg_name_scale = "scale";
+    let scale_arg = Arg::with_name(arg_name_scale)
+        .long(arg_name_scale)
+        .help("Image pixels for each map pixel (one map pixel is 5 cm), at most 32")
+        .takes_value(true)
+        .default_value("4");
+
+    let arg_name_rotate = "rotate";
+    let rotate_arg = Arg::with_name(arg_name_rotate)
+        .long(arg_name_rotate)
+        .help("Clockwise rotation of the image, in degrees")
+        .takes_value(true)
+        .possible_values(&["0", "90", "180", "270"])
+        .default_value("0");
+
+    let arg_name_crop = "crop";
+    let crop_arg = Arg::with_name(arg_name_crop)
+        .long(arg_name_crop)
+        .help("Crop the image to the explored area of the map");
+
+    let arg_name_layers = "layers";
+    let layers_arg = Arg::with_name(arg_name_layers)
+        .long(arg_name_layers)
+        .help("Layers to draw: all, or a list of floor, walls, segments, path, robot, charger, restrictions")
+        .takes_value(true)
+        .default_value("all");
+
+    let arg_name_save = "save";
+    let save_arg = Arg::with_name(arg_name_save)
+        .long(arg_name_save)
+        .help("Save the restrictions of the map given with --input to a JSON or YAML (.yaml, .yml) file")
+        .takes_value(true)
+        .requires(arg_name_input);
+
+    let arg_name_load = "load";
+    let load_arg = Arg::with_name(arg_name_load)
+        .long(arg_name_load)
+        .help("Load restrictions from a JSON or YAML (.yaml, .yml) file, and save them on the robot. \
+               Restrictions which are not in the file are removed from the robot.")
+        .takes_value(true)
+        .conflicts_with(arg_name_save);
+
+    let arg_name_lab_mode = "lab-mode";
+    let lab_mode_arg = Arg::with_name(arg_name_lab_mode)
+        .long(arg_name_lab_mode)
+        .help("Enable the lab mode before saving the restrictions, as needed by older firmware")
+        .requires(arg_name_load);
+
+    let arg_name_rename = "rename";
+    let rename_arg = Arg::with_name(arg_name_rename)
+        .long(arg_name_rename)
+        .help("Rename a room on the robot, as SEGMENT=NAME (e.g. 16=Kitchen). Can be given multiple times.")
+        .takes_value(true)
+        .multiple(true)
+        .number_of_values(1)
+        .conflicts_with(arg_name_clean);
+
+    let arg_name_map = "map";
+    let load_map_arg = Arg::with_name(arg_name_map)
+        .long(arg_name_map)
+        .help("Name or flag of the saved map to load")
+        .takes_value(true);
+
+    let arg_name_boost = "boost";
+    let boost_arg = Arg::with_name(arg_name_boost)
+        .long(arg_name_boost)
+        .help("Raise the fan speed on carpets")
+        .takes_value(true)
+        .possible_values(&["on", "off"]);
+
+    let arg_name_current_high = "current-high";
+    let current_high_arg = Arg::with_name(arg_name_current_high)
+        .long(arg_name_current_high)
+        .help("Brush motor current above which a carpet is detected")
+        .takes_value(true);
+
+    let arg_name_current_low = "current-low";
+    let current_low_arg = Arg::with_name(arg_name_current_low)
+        .long(arg_name_current_low)
+        .help("Brush motor current below which the robot is no longer on a carpet")
+        .takes_value(true);
+
+    let arg_name_current_integral = "current-integral";
+    let current_integral_arg = Arg::with_name(arg_name_current_integral)
+        .long(arg_name_current_integral)
+        .help("Integral of the brush motor current above which a carpet is detected")
+        .takes_value(true);
+
+    let arg_name_stall_time = "stall-time";
+    let stall_time_arg = Arg::with_name(arg_name_stall_time)
+        .long(arg_name_stall_time)
+        .help("Seconds to keep the boost after leaving a carpet")
+        .takes_value(true);
+
+    let arg_name_clean_mode = "clean-mode";
+    let clean_mode_arg = Arg::with_name(arg_name_clean_mode)
+        .long(arg_name_clean_mode)
+        .help("What to do with carpets while mopping")
+        .takes_value(true)
+        .possible_values(&["avoid", "rise", "ignore"]);
+
+    let matches = App::new("roborockutil")
+        .version("0.1.0")
+        .author("Bogdan Olar <olar.bogdan.dev@gmail.com>")
+        .about("RoboRock S5 utility")
+        .arg(sip_arg.clone()
+            .required(false))
+        .subcommand(SubCommand::with_name(arg_cmd_name_discover)
+            .about("Discover miio devices")
+            .arg(sip_arg.clone()
+                .required(true))
+            .arg(dip_arg.clone()
+                .required(false)))
+        .subcommand(SubCommand::with_name(arg_cmd_name_status)
+            .about("Get device status")
+            .arg(sip_arg.clone()
+                .required(true))
+            .arg(dip_arg.clone()
+                .required(true))
+            .arg(token_arg.clone()
+                .required(true)))
+        .subcommand(SubCommand::with_name(arg_cmd_name_info)
+            .about("Get device information")
+            .arg(sip_arg.clone()
+                .required(true))
+            .arg(dip_arg.clone()
+                .required(true))
+            .arg(token_arg.clone()
+                .required(true)))
+        .subcommand(SubCommand::with_name(arg_cmd_name_command)
+            .about("Send any miio method to the device, and print the result")
+            .arg(sip_arg.clone()
+                .required(true))
+            .arg(dip_arg.clone()
+                .required(true))
+            .arg(token_arg.clone()
+                .required(true))
+            .arg(method_arg.clone()
+                .required(true))
+            .arg(params_arg.clone()))
+        .subcommand(SubCommand::with_name(arg_cmd_name_provision)
+            .about("Discover a device in provisioning mode, and connect it to a wifi network")
+            .arg(sip_arg.clone()
+                .required(true))
+            .arg(dip_arg.clone()
+                .required(false))
+            .arg(ssid_arg.clone()
+                .required(true))
+            .arg(passwd_arg.clone()
+                .required(true))
+            .arg(uid_arg.clone()
+                .required(true))
+            .arg(country_arg.clone()
+                .required(false))
+            .arg(tz_arg.clone()
+                .required(false)))
+        .subcommand(SubCommand::with_name(arg_cmd_name_fanspeed)
+            .about("Get or set the suction power")
+            .arg(sip_arg.clone()
+                .required(true))
+            .arg(dip_arg.clone()
+                .required(true))
+            .arg(token_arg.clone()
+                .required(true))
+            .arg(fanspeed_set_arg.clone()
+                .required(false))
+            .arg(scheme_arg.clone()
+                .required(false)))
+        .subcommand(SubCommand::with_name(arg_cmd_name_consumables)
+            .about("Get the usage of the consumables, or reset one of them")
+            .arg(sip_arg.clone()
+                .required(true))
+            .arg(dip_arg.clone()
+                .required(true))
+            .arg(token_arg.clone()
+                .required(true))
+            .arg(reset_arg.clone()
+                .required(false)))
+        .subcommand(SubCommand::with_name(arg_cmd_name_history)
+            .about("Get the cleaning totals and the most recent clean records")
+            .arg(sip_arg.clone()
+                .required(true))
+            .arg(dip_arg.clone()
+                .required(true))
+            .arg(token_arg.clone()
+                .required(true))
+            .arg(limit_arg.clone())
+            .subcommand(SubCommand::with_name(arg_cmd_name_export)
+                .about("Append the clean records which are not yet exported to a file")
+                .arg(format_arg.clone())
+                .arg(output_arg.clone()
+                    .required(true))))
+        .subcommand(SubCommand::with_name(arg_cmd_name_zoned)
+            .about("Clean one or more rectangular zones")
+            .arg(sip_arg.clone()
+                .required(true))
+            .arg(dip_arg.clone()
+                .required(true))
+            .arg(token_arg.clone()
+                .required(true))
+            .arg(zone_arg.clone()
+                .required_unless(arg_name_name))
+            .arg(zones_file_arg.clone()
+                .requires(arg_name_name))
+            .arg(name_arg.clone()
+                .requires(arg_name_zones_file)
+                .conflicts_with(arg_name_zone)))
+        .subcommand(SubCommand::with_name(arg_cmd_name_goto)
+            .about("Drive to a point of the map")
+            .arg(sip_arg.clone()
+                .required(true))
+            .arg(dip_arg.clone()
+                .required(true))
+            .arg(token_arg.clone()
+                .required(true))
+            .arg(x_arg.clone()
+                .required(true))
+            .arg(y_arg.clone()
+                .required(true)))
+        .subcommand(SubCommand::with_name(arg_cmd_name_drive)
+            .about("Drive the robot interactively: w/s forward/backward, a/d turn left/right, q quit. \
+                    Each key is followed by Enter, and several keys can be entered at once, e.g. `www`.")
+            .arg(sip_arg.clone()
+                .required(true))
+            .arg(dip_arg.clone()
+                .required(true))
+            .arg(token_arg.clone()
+                .required(true))
+            .arg(velocity_arg.clone())
+            .arg(rotation_arg.clone()))
+        .subcommand(SubCommand::with_name(arg_cmd_name_rooms)
+            .about("List the rooms of the current map, or clean rooms by name")
+            .arg(sip_arg.clone()
+                .required(true))
+            .arg(dip_arg.clone()
+                .required(true))
+            .arg(token_arg.clone()
+                .required(true))
+            .arg(names_file_arg.clone()
+                .required(false))
+            .arg(clean_arg.clone()
+                .requires(arg_name_names_file))
+            .arg(repeats_arg.clone())
+            .arg(rename_arg.clone()))
+        .subcommand(SubCommand::with_name(arg_cmd_name_dnd)
+            .about("Get, set or disable the Do-Not-Disturb quiet hours")
+            .arg(sip_arg.clone()
+                .required(true))
+            .arg(dip_arg.clone()
+                .required(true))
+            .arg(token_arg.clone()
+                .required(true))
+            .arg(window_arg.clone()
+                .required(false))
+            .arg(disable_arg.clone()))
+        .subcommand(SubCommand::with_name(arg_cmd_name_timers)
+            .about("List the cleaning timers stored on the robot")
+            .arg(sip_arg.clone()
+                .required(true))
+            .arg(dip_arg.clone()
+                .required(true))
+            .arg(token_arg.clone()
+                .required(true))
+            .subcommand(SubCommand::with_name(arg_cmd_name_add)
+                .about("Add a cleaning timer")
+                .arg(time_arg.clone()
+                    .required(true))
+                .arg(days_arg.clone())
+                .arg(timer_fanspeed_arg.clone()
+                    .required(false))
+                .arg(scheme_arg.clone()
+                    .required(false))
+                .arg(segments_arg.clone()
+                    .required(false)))
+            .subcommand(SubCommand::with_name(arg_cmd_name_enable)
+                .about("Enable a cleaning timer")
+                .arg(timer_id_arg.clone()
+                    .required(true)))
+            .subcommand(SubCommand::with_name(arg_cmd_name_disable)
+                .about("Disable a cleaning timer")
+                .arg(timer_id_arg.clone()
+                    .required(true)))
+            .subcommand(SubCommand::with_name(arg_cmd_name_delete)
+                .about("Delete a cleaning timer")
+                .arg(timer_id_arg.clone()
+                    .required(true))))
+        .subcommand(SubCommand::with_name(arg_cmd_name_sound)
+            .about("Get or set the sound volume, or install a voice pack")
+            .arg(sip_arg.clone()
+                .required(true))
+            .arg(dip_arg.clone()
+                .required(true))
+            .arg(token_arg.clone()
+                .required(true))
+            .arg(volume_arg.clone()
+                .required(false))
+            .arg(test_arg.clone())
+            .subcommand(SubCommand::with_name(arg_cmd_name_install)
+                .about("Install a voice pack, and wait for the installation to end")
+                .arg(url_arg.clone()
+                    .required_unless(arg_name_file)
+                    .requires(arg_name_md5))
+                .arg(file_arg.clone())
+                .arg(md5_arg.clone()
+                    .required(false))
+                .arg(port_arg.clone())
+                .arg(sid_arg.clone()
+                    .required(true))))
+        .subcommand(SubCommand::with_name(arg_cmd_name_ota)
+            .about("Update the firmware, and wait for the update to end. The robot must be docked.")
+            .arg(sip_arg.clone()
+                .required(true))
+            .arg(dip_arg.clone()
+                .required(true))
+            .arg(token_arg.clone()
+                .required(true))
+            .arg(url_arg.clone()
+                .required_unless(arg_name_file)
+                .requires(arg_name_md5))
+            .arg(file_arg.clone())
+            .arg(md5_arg.clone()
+                .required(false))
+            .arg(port_arg.clone())
+            .arg(min_battery_arg.clone()
+                .required(false)))
+        .subcommand(SubCommand::with_name(arg_cmd_name_map)
+            .about("Get the URL of the map, or show the contents of a downloaded map")
+            .arg(sip_arg.clone()
+                .required_unless(arg_name_input))
+            .arg(dip_arg.clone()
+                .required_unless(arg_name_input))
+            .arg(token_arg.clone()
+                .required_unless(arg_name_input))
+            .arg(input_arg.clone()
+                .required(false))
+            .setting(AppSettings::SubcommandsNegateReqs)
+            .subcommand(SubCommand::with_name(arg_cmd_name_render)
+                .about("Render a downloaded map to a PNG or SVG image")
+                .arg(input_arg.clone()
+                    .required(true))
+                .arg(image_output_arg.clone()
+                    .required(true))
+                .arg(image_format_arg.clone()
+                    .required(false))
+                .arg(scale_arg.clone())
+                .arg(rotate_arg.clone())
+                .arg(crop_arg.clone())
+                .arg(layers_arg.clone())))
+        .subcommand(SubCommand::with_name(arg_cmd_name_restrictions)
+            .about("Save the no-go zones, no-mop zones and virtual walls of a map to a file, or restore them from a file")
+            .arg(sip_arg.clone()
+                .required_unless(arg_name_save))
+            .arg(dip_arg.clone()
+                .required_unless(arg_name_save))
+            .arg(token_arg.clone()
+                .required_unless(arg_name_save))
+            .arg(input_arg.clone()
+                .required(false))
+            .arg(save_arg.clone()
+                .required_unless(arg_name_load))
+            .arg(load_arg.clone()
+                .required_unless(arg_name_save))
+            .arg(lab_mode_arg.clone()))
+        .subcommand(SubCommand::with_name(arg_cmd_name_maps)
+            .about("List the saved maps (e.g. one for each floor), or load one of them")
+            .arg(sip_arg.clone()
+                .required(true))
+            .arg(dip_arg.clone()
+                .required(true))
+            .arg(token_arg.clone()
+                .required(true))
+            .arg(load_map_arg.clone()
+                .required(false)))
+        .subcommand(SubCommand::with_name(arg_cmd_name_carpet)
+            .about("Get or set the suction boost on carpets, and what to do with carpets while mopping")
+            .arg(sip_arg.clone()
+                .required(true))
+            .arg(dip_arg.clone()
+                .required(true))
+            .arg(token_arg.clone()
+                .required(true))
+            .arg(boost_arg.clone())
+            .arg(current_high_arg.clone())
+            .arg(current_low_arg.clone())
+            .arg(current_integral_arg.clone())
+            .arg(stall_time_arg.clone())
+            .arg(clean_mode_arg.clone()))
+        .subcommands(control_cmds.iter().map(|(name, about, _cmd)| {
+            SubCommand::with_name(name)
+                .about(*about)
+                .arg(sip_arg.clone()
+                    .required(true))
+                .arg(dip_arg.clone()
+                    .required(true))
+                .arg(token_arg.clone()
+                    .required(true))
+        }))
+        .get_matches();
+
+    if let Some(discover_cmd) = matches.subcommand_matches(arg_cmd_name_discover) {
+        // process required arguments
+        let sip = arg_get_ip(arg_name_sip, &discover_cmd).unwrap_or_else(|e|  {
+            eprintln!("{}", e);
+            process::exit(1);
+        });
+
+        // process optional arguments
+        let dip_opt;
+        match arg_get_ip(arg_name_dip, &discover_cmd) {
+            Ok(ip) => dip_opt = Some(ip),
+            Err(_e) => dip_opt = None
+        }
+
+        // create UDP socket
+        let socket = UdpSocket::bind(sip.to_string() + ":" + MI_DISCOVER_UDP_PORT.to_string().as_str())
+            .unwrap_or_else(|e|  {
+                eprintln!("{}", e);
+                process::exit(1);
+            });
+
+        // do discovery
+        match discovery::discover(socket, dip_opt) {
+            Ok(responses) => { print_discover_results(&responses); }
+            Err(e) => { eprintln!("{}", e); }
+        }
+    }
+
+    if let Some(status_cmd) = matches.subcommand_matches(arg_cmd_name_status) {
+        let mut device = device_connect(status_cmd, arg_name_sip, arg_name_dip, arg_name_token);
+
+        // get device status
+        match device.status() {
+            Ok(resp) => { print_status(&resp); }
+            Err(e) => { eprintln!("{}", e); }
+        }
+    }
+
+    if let Some(info_cmd) = matches.subcommand_matches(arg_cmd_name_info) {
+        let mut device = device_connect(info_cmd, arg_name_sip, arg_name_dip, arg_name_token);
+
+        // get device information
+        match device.info() {
+            Ok(info) => { println!("{}", serde_json::to_string_pretty(&info).unwrap()); }
+            Err(e) => { eprintln!("{}", e); }
+        }
+    }
+
+    if let Some(command_cmd) = matches.subcommand_matches(arg_cmd_name_command) {
+        // process required arguments
+        let method = command_cmd.value_of(arg_name_method).unwrap_or_else(|| {
+            eprintln!("{}", ArgError::NotFound(arg_name_method.to_string()));
+            process::exit(1);
+        });
+        let params = arg_get_json(arg_name_params, &command_cmd).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            process::exit(1);
+        });
+
+        let mut device = device_connect(command_cmd, arg_name_sip, arg_name_dip, arg_name_token);
+
+        match device.send_command(method, params) {
+            Ok(result) => { println!("{}", result); }
+            Err(e) => { eprintln!("{}", e); }
+        }
+    }
+
+    if let Some(provision_cmd) = matches.subcommand_matches(arg_cmd_name_provision) {
+        // process required arguments
+        let sip = arg_get_ip(arg_name_sip, &provision_cmd).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            process::exit(1);
+        });
+        let ssid = provision_cmd.value_of(arg_name_ssid).unwrap_or_else(|| {
+            eprintln!("{}", ArgError::NotFound(arg_name_ssid.to_string()));
+            process::exit(1);
+        });
+        let passwd = provision_cmd.value_of(arg_name_passwd).unwrap_or_else(|| {
+            eprintln!("{}", ArgError::NotFound(arg_name_passwd.to_string()));
+            process::exit(1);
+        });
+        let uid = arg_get_value::<u64>(arg_name_uid, &provision_cmd).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            process::exit(1);
+        });
+
+        // process optional arguments
+        let dip_opt = arg_get_ip(arg_name_dip, &provision_cmd).ok();
+        let country_opt = provision_cmd.value_of(arg_name_country);
+        let tz_opt = provision_cmd.value_of(arg_name_tz);
+
+        // create UDP socket
+        let socket = UdpSocket::bind(sip.to_string() + ":" + MI_DISCOVER_UDP_PORT.to_string().as_str())
+            .unwrap_or_else(|e|  {
+                eprintln!("{}", e);
+                process::exit(1);
+            });
+
+        // discover, capture the provisioning token, and configure the router
+        match provisioning::provision(socket, dip_opt, ssid, passwd, uid, country_opt, tz_opt) {
+            Ok(dip) => { println!("Device {} configured to connect to {}", dip, ssid); }
+            Err(e) => { eprintln!("{}", e); }
+        }
+    }
+
+    for (name, _about, cmd) in control_cmds.iter() {
+        if let Some(control_cmd) = matches.subcommand_matches(name) {
+            let mut device = device_connect(control_cmd, arg_name_sip, arg_name_dip, arg_name_token);
+
+            let resp = match cmd {
+                ControlCommand::Home => device.home(),
+                _ => device.control(*cmd)
+            };
+            match resp {
+                Ok(()) => { println!("ok"); }
+                Err(e) => { eprintln!("{}", e); }
+            }
+        }
+    }
+
+    if let Some(fanspeed_cmd) = matches.subcommand_matches(arg_cmd_name_fanspeed) {
+        // process optional arguments
+        let speed_opt = match fanspeed_cmd.value_of(arg_name_set) {
+            Some(_) => Some(arg_get_value::<FanSpeed>(arg_name_set, &fanspeed_cmd).unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                process::exit(1);
+            })),
+            None => None
+        };
+        let scheme_opt = arg_get_value::<FanSpeedScheme>(arg_name_scheme, &fanspeed_cmd).ok();
+
+        let mut device = device_connect(fanspeed_cmd, arg_name_sip, arg_name_dip, arg_name_token);
+
+        if let Some(speed) = speed_opt {
+            if let Err(e) = device.set_fan_speed(speed, scheme_opt) {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+        }
+        match device.fan_power() {
+            Ok(fan_power) => { println!("Fan speed: {} ({})", FanSpeed::from(fan_power), fan_power); }
+            Err(e) => { eprintln!("{}", e); }
+        }
+    }
+
+    if let Some(consumables_cmd) = matches.subcommand_matches(arg_cmd_name_consumables) {
+        // process optional arguments
+        let reset_opt = arg_get_value::<Consumable>(arg_name_reset, &consumables_cmd).ok();
+
+        let mut device = device_connect(consumables_cmd, arg_name_sip, arg_name_dip, arg_name_token);
+
+        if let Some(consumable) = reset_opt {
+            if let Err(e) = device.reset_consumable(consumable) {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+        }
+        match device.consumables() {
+            Ok(consumables) => { print_consumables(&consumables); }
+            Err(e) => { eprintln!("{}", e); }
+        }
+    }
+
+    if let Some(history_cmd) = matches.subcommand_matches(arg_cmd_name_history) {
+        let limit = arg_get_value::<usize>(arg_name_limit, &history_cmd).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            process::exit(1);
+        });
+
+        let mut device = device_connect(history_cmd, arg_name_sip, arg_name_dip, arg_name_token);
+
+        if let Some(export_cmd) = history_cmd.subcommand_matches(arg_cmd_name_export) {
+            let format = arg_get_value::<ExportFormat>(arg_name_format, &export_cmd).unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                process::exit(1);
+            });
+            let output = export_cmd.value_of(arg_name_output).unwrap_or_else(|| {
+                eprintln!("{}", ArgError::NotFound(arg_name_output.to_string()));
+                process::exit(1);
+            });
+
+            match history::export(&mut device, Path::new(output), format) {
+                Ok(count) => { println!("Exported {} new records to {}", count, output); }
+                Err(e) => {
+                    eprintln!("{}", e);
+                    process::exit(1);
+                }
+            }
+            return;
+        }
+
+        let summary = device.clean_summary().unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            process::exit(1);
+        });
+        println!("Total clean time: {} h", summary.total_time().as_secs() / 3600);
+        println!("Total clean area: {:.2} m²", summary.total_area());
+        println!("Clean count: {}", summary.clean_count);
+
+        for id in summary.records.iter().take(limit) {
+            match device.clean_record(*id) {
+                Ok(record) => { print_clean_record(&record); }
+                Err(e) => { eprintln!("{}", e); }
+            }
+        }
+    }
+
+    if let Some(zoned_cmd) = matches.subcommand_matches(arg_cmd_name_zoned) {
+        let zones = if let Some(name) = zoned_cmd.value_of(arg_name_name) {
+            // named zones, from the zones file
+            let zones_file = zoned_cmd.value_of(arg_name_zones_file).unwrap_or_else(|| {
+                eprintln!("{}", ArgError::NotFound(arg_name_zones_file.to_string()));
+                process::exit(1);
+            });
+            let mut named_zones = zone::load_named_zones(Path::new(zones_file)).unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                process::exit(1);
+            });
+            named_zones.remove(name).unwrap_or_else(|| {
+                eprintln!("{}", ArgError::Parse(arg_name_name.to_string(), name.to_string()));
+                process::exit(1);
+            })
+        } else {
+            // inline zones
+            zoned_cmd.values_of(arg_name_zone).unwrap_or_default().map(|zone_str| {
+                Zone::from_str(zone_str).unwrap_or_else(|e| {
+                    eprintln!("{}", ArgError::Parse(arg_name_zone.to_string(), e));
+                    process::exit(1);
+                })
+            }).collect()
+        };
+        if let Err(e) = zone::validate_zones(&zones) {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+
+        let mut device = device_connect(zoned_cmd, arg_name_sip, arg_name_dip, arg_name_token);
+
+        match device.zoned_clean(&zones) {
+            Ok(()) => { println!("ok"); }
+            Err(e) => { eprintln!("{}", e); }
+        }
+    }
+
+    if let Some(goto_cmd) = matches.subcommand_matches(arg_cmd_name_goto) {
+        // process required arguments
+        let x = arg_get_value::<u32>(arg_name_x, &goto_cmd).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            process::exit(1);
+        });
+        let y = arg_get_value::<u32>(arg_name_y, &goto_cmd).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            process::exit(1);
+        });
+
+        let mut device = device_connect(goto_cmd, arg_name_sip, arg_name_dip, arg_name_token);
+
+        match device.goto(x, y) {
+            Ok(()) => { println!("ok"); }
+            Err(e) => { eprintln!("{}", e); }
+        }
+    }
+
+    if let Some(drive_cmd) = matches.subcommand_matches(arg_cmd_name_drive) {
+        let velocity = arg_get_value::<f64>(arg_name_velocity, &drive_cmd).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            process::exit(1);
+        });
+        let rotation = arg_get_value::<f64>(arg_name_rotation, &drive_cmd).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            process::exit(1);
+        });
+
+        let mut device = device_connect(drive_cmd, arg_name_sip, arg_name_dip, arg_name_token);
+
+        if let Err(e) = drive(&mut device, velocity, rotation) {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    }
+
+    if let Some(rooms_cmd) = matches.subcommand_matches(arg_cmd_name_rooms) {
+        // process optional arguments
+        let names = match rooms_cmd.value_of(arg_name_names_file) {
+            Some(names_file) => room::load_room_names(Path::new(names_file)).unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                process::exit(1);
+            }),
+            None => room::RoomNames::new()
+        };
+        let rooms: Vec<&str> = rooms_cmd.values_of(arg_name_clean).unwrap_or_default().collect();
+        let repeats = arg_get_value::<u32>(arg_name_repeats, &rooms_cmd).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            process::exit(1);
+        });
+        let segments = room::segments_for(&names, &rooms).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            process::exit(1);
+        });
+        let renames: Vec<(u32, String)> = rooms_cmd.values_of(arg_name_rename).unwrap_or_default().map(|r| {
+            room::parse_rename(r).unwrap_or_else(|e| {
+                eprintln!("{}", ArgError::Parse(arg_name_rename.to_string(), e));
+                process::exit(1);
+            })
+        }).collect();
+
+        let mut device = device_connect(rooms_cmd, arg_name_sip, arg_name_dip, arg_name_token);
+
+        for (segment, name) in renames {
+            if let Err(e) = device.name_segment(segment, &name) {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+        }
+
+        if segments.is_empty() {
+            match device.room_mapping() {
+                Ok(mapping) => {
+                    for m in mapping {
+                        println!("\t--segment {}\t(room {})\t{}", m.segment_id, m.room_id,
+                                 room::name_of(&names, m.segment_id).unwrap_or(""));
+                    }
+                }
+                Err(e) => { eprintln!("{}", e); }
+            }
+        } else {
+            match device.segment_clean(&segments, repeats) {
+                Ok(()) => { println!("ok"); }
+                Err(e) => { eprintln!("{}", e); }
+            }
+        }
+    }
+
+    if let Some(dnd_cmd) = matches.subcommand_matches(arg_cmd_name_dnd) {
+        // process optional arguments
+        let window_opt = match dnd_cmd.value_of(arg_name_window) {
+            Some(_) => Some(arg_get_value::<DndWindow>(arg_name_window, &dnd_cmd).unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                process::exit(1);
+            })),
+            None => None
+        };
+
+        let mut device = device_connect(dnd_cmd, arg_name_sip, arg_name_dip, arg_name_token);
+
+        if let Some(window) = window_opt {
+            if let Err(e) = device.set_dnd_window(window) {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+        }
+        if dnd_cmd.is_present(arg_name_disable) {
+            if let Err(e) = device.disable_dnd() {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+        }
+        match device.dnd_window() {
+            Ok(window) => { println!("Do-Not-Disturb: {}", window); }
+            Err(e) => { eprintln!("{}", e); }
+        }
+    }
+
+    if let Some(timers_cmd) = matches.subcommand_matches(arg_cmd_name_timers) {
+        let mut device = device_connect(timers_cmd, arg_name_sip, arg_name_dip, arg_name_token);
+
+        let resp = if let Some(add_cmd) = timers_cmd.subcommand_matches(arg_cmd_name_add) {
+            let time = add_cmd.value_of(arg_name_time).unwrap_or_default();
+            let (hour, minute) = dnd::parse_time(time).unwrap_or_else(|_e| {
+                eprintln!("{}", ArgError::Parse(arg_name_time.to_string(), time.to_string()));
+                process::exit(1);
+            });
+            let weekdays = arg_get_value::<Weekdays>(arg_name_days, &add_cmd).unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                process::exit(1);
+            });
+
+            let mut timer = CleanTimer::new(hour, minute, weekdays);
+            if add_cmd.value_of(arg_name_fanspeed).is_some() {
+                let speed = arg_get_value::<FanSpeed>(arg_name_fanspeed, &add_cmd).unwrap_or_else(|e| {
+                    eprintln!("{}", e);
+                    process::exit(1);
+                });
+                // the timer stores a numeric suction power, so the scheme of the firmware is needed for presets
+                let scheme = match arg_get_value::<FanSpeedScheme>(arg_name_scheme, &add_cmd) {
+                    Ok(scheme) => scheme,
+                    Err(_e) => device.fan_speed_scheme().unwrap_or_else(|e| {
+                        eprintln!("{}", e);
+                        process::exit(1);
+                    })
+                };
+                timer.fan_power = Some(speed.fan_power(scheme));
+            }
+            if let Some(segments) = add_cmd.value_of(arg_name_segments) {
+                let ids: Result<Vec<u32>, _> = segments.split(',').map(|s| s.trim().parse::<u32>()).collect();
+                timer.segments = Some(ids.unwrap_or_else(|_e| {
+                    eprintln!("{}", ArgError::Parse(arg_name_segments.to_string(), segments.to_string()));
+                    process::exit(1);
+                }));
+            }
+            device.add_timer(&timer)
+        } else if let Some(enable_cmd) = timers_cmd.subcommand_matches(arg_cmd_name_enable) {
+            device.set_timer_enabled(enable_cmd.value_of(arg_name_id).unwrap_or_default(), true)
+        } else if let Some(disable_cmd) = timers_cmd.subcommand_matches(arg_cmd_name_disable) {
+            device.set_timer_enabled(disable_cmd.value_of(arg_name_id).unwrap_or_default(), false)
+        } else if let Some(delete_cmd) = timers_cmd.subcommand_matches(arg_cmd_name_delete) {
+            device.delete_timer(delete_cmd.value_of(arg_name_id).unwrap_or_default())
+        } else {
+            Ok(())
+        };
+        if let Err(e) = resp {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+
+        match device.timers() {
+            Ok(timers) => {
+                for timer in timers {
+                    match timer {
+                        Ok(timer) => { println!("\t--id {}\t{}", timer.id, timer); }
+                        Err(e) => { eprintln!("Skipping {}", e); }
+                    }
+                }
+            }
+            Err(e) => { eprintln!("{}", e); }
+        }
+    }
+
+    if let Some(sound_cmd) = matches.subcommand_matches(arg_cmd_name_sound) {
+        // process optional arguments
+        let volume_opt = match sound_cmd.value_of(arg_name_volume) {
+            Some(_) => Some(arg_get_value::<u32>(arg_name_volume, &sound_cmd).unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                process::exit(1);
+            })),
+            None => None
+        };
+
+        let mut device = device_connect(sound_cmd, arg_name_sip, arg_name_dip, arg_name_token);
+
+        if let Some(volume) = volume_opt {
+            if let Err(e) = device.set_sound_volume(volume) {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+        }
+        if let Some(install_cmd) = sound_cmd.subcommand_matches(arg_cmd_name_install) {
+            let sid = arg_get_value::<u32>(arg_name_sid, &install_cmd).unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                process::exit(1);
+            });
+            // the server (if any) must keep running until the robot has downloaded the package
+            let (_server, url, md5) = package_source(sound_cmd, install_cmd, arg_name_sip, arg_name_url,
+                                                     arg_name_file, arg_name_md5, arg_name_port);
+            println!("Installing voice pack {} from {}", sid, url);
+
+            if let Err(e) = device.install_sound(&SoundInstall { url, md5, sid }) {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+            match wait_sound_install(&mut device) {
+                Ok((SoundInstallState::Installed, _)) => { println!("ok"); }
+                Ok((state, progress)) => {
+                    eprintln!("Installation failed: {} (error {})", state, progress.error);
+                    process::exit(1);
+                }
+                Err(e) => {
+                    eprintln!("{}", e);
+                    process::exit(1);
+                }
+            }
+        }
+        if sound_cmd.is_present(arg_name_test) {
+            if let Err(e) = device.test_sound_volume() {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+        }
+        match device.sound_volume() {
+            Ok(volume) => { println!("Volume: {}", volume); }
+            Err(e) => { eprintln!("{}", e); }
+        }
+        match device.current_sound() {
+            Ok(sound) => { println!("Voice pack: {}", sound.sid_in_use); }
+            Err(e) => { eprintln!("{}", e); }
+        }
+    }
+
+    if let Some(ota_cmd) = matches.subcommand_matches(arg_cmd_name_ota) {
+        // process optional arguments
+        let min_battery = match ota_cmd.value_of(arg_name_min_battery) {
+            Some(_) => arg_get_value::<u32>(arg_name_min_battery, &ota_cmd).unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                process::exit(1);
+            }),
+            None => ota::MIN_BATTERY
+        };
+
+        let mut device = device_connect(ota_cmd, arg_name_sip, arg_name_dip, arg_name_token);
+
+        // the server (if any) must keep running until the robot has downloaded the firmware
+        let (_server, url, md5) = package_source(ota_cmd, ota_cmd, arg_name_sip, arg_name_url, arg_name_file,
+                                                 arg_name_md5, arg_name_port);
+        println!("Updating firmware from {}", url);
+
+        if let Err(e) = device.ota_update(&url, &md5, min_battery) {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+        match wait_ota(&mut device) {
+            Ok(OtaState::Installed) => { println!("ok"); }
+            Ok(OtaState::Idle) => {
+                eprintln!("The robot did not start the update");
+                process::exit(1);
+            }
+            Ok(state) => {
+                eprintln!("Update failed: {}", state);
+                process::exit(1);
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+        }
+    }
 
-    let arg_name_sip = "sip";
-    let sip_arg = Arg::with_name(arg_name_sip)
-        .long(arg_name_sip)
-        .help("The local IP on which to open the UDP socket")
-        .takes_value(true);
+    if let Some(map_cmd) = matches.subcommand_matches(arg_cmd_name_map) {
+        if let Some(render_cmd) = map_cmd.subcommand_matches(arg_cmd_name_render) {
+            let input = render_cmd.value_of(arg_name_input).unwrap_or_default();
+            let output = Path::new(render_cmd.value_of(arg_name_output).unwrap_or_default());
+            let format = match render_cmd.value_of(arg_name_format) {
+                Some(_) => arg_get_value::<ImageFormat>(arg_name_format, &render_cmd).ok(),
+                None => ImageFormat::from_path(output)
+            }.unwrap_or_else(|| {
+                eprintln!("{}", ArgError::NotFound(arg_name_format.to_string()));
+                process::exit(1);
+            });
+            let mut options = RenderOptions::default();
+            options.crop = render_cmd.is_present(arg_name_crop);
+            options.scale = arg_get_value::<u32>(arg_name_scale, &render_cmd).unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                process::exit(1);
+            });
+            options.rotation = arg_get_value::<Rotation>(arg_name_rotate, &render_cmd).unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                process::exit(1);
+            });
+            options.layers = arg_get_value::<Layers>(arg_name_layers, &render_cmd).unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                process::exit(1);
+            });
 
-    let arg_name_dip= "dip";
-    let dip_arg = Arg::with_name(arg_name_dip)
-        .long(arg_name_dip)
-        .help("The IP of the target device.")
-        .takes_value(true);
+            let data = fs::read(input).unwrap_or_else(|e| {
+                eprintln!("{}: {}", input, e);
+                process::exit(1);
+            });
+            let rrmap = map::parse(&data).unwrap_or_else(|e| {
+                eprintln!("{}: {}", input, e);
+                process::exit(1);
+            });
+            if let Err(e) = render::render_file(&rrmap, &options, format, output) {
+                eprintln!("{}: {}", output.display(), e);
+                process::exit(1);
+            }
+            return;
+        }
 
-    let arg_name_token = "token";
-    let token_arg = Arg::with_name(arg_name_token)
-        .long(arg_name_token)
-        .help("Token used for encryption/decryption (16 alphanumeric characters)")
-        .takes_value(true);
+        if let Some(input) = map_cmd.value_of(arg_name_input) {
+            let data = fs::read(input).unwrap_or_else(|e| {
+                eprintln!("{}: {}", input, e);
+                process::exit(1);
+            });
+            match map::parse(&data) {
+                Ok(rrmap) => { print_map(&rrmap); }
+                Err(e) => {
+                    eprintln!("{}: {}", input, e);
+                    process::exit(1);
+                }
+            }
+        } else {
+            let mut device = device_connect(map_cmd, arg_name_sip, arg_name_dip, arg_name_token);
 
-    let arg_name_did = "did";
-    let did_arg = Arg::with_name(arg_name_did)
-        .long(arg_name_did)
-        .help("Device ID")
-        .takes_value(true);
+            match device.map_url() {
+                Ok(url) => { println!("{}", url); }
+                Err(e) => { eprintln!("{}", e); }
+            }
+        }
+    }
 
-    let arg_name_stamp = "stamp";
-    let stamp_arg = Arg::with_name(arg_name_stamp)
-        .long(arg_name_stamp)
-        .help("Packet stamp")
-        .takes_value(true);
+    if let Some(restrictions_cmd) = matches.subcommand_matches(arg_cmd_name_restrictions) {
+        if let Some(save) = restrictions_cmd.value_of(arg_name_save) {
+            let input = restrictions_cmd.value_of(arg_name_input).unwrap_or_default();
+            let data = fs::read(input).unwrap_or_else(|e| {
+                eprintln!("{}: {}", input, e);
+                process::exit(1);
+            });
+            let rrmap = map::parse(&data).unwrap_or_else(|e| {
+                eprintln!("{}: {}", input, e);
+                process::exit(1);
+            });
+            let restrictions = Restrictions::from_map(&rrmap);
+            match restrictions.save(Path::new(save)) {
+                Ok(()) => {
+                    println!("Saved {} no-go zones, {} no-mop zones and {} virtual walls to {}",
+                             restrictions.no_go_zones.len(), restrictions.no_mop_zones.len(),
+                             restrictions.virtual_walls.len(), save);
+                }
+                Err(e) => {
+                    eprintln!("{}", e);
+                    process::exit(1);
+                }
+            }
+        } else {
+            let load = restrictions_cmd.value_of(arg_name_load).unwrap_or_default();
+            let restrictions = Restrictions::load(Path::new(load)).unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                process::exit(1);
+            });
 
-    let arg_name_cmdid = "cmdid";
-    let cmdid_arg = Arg::with_name(arg_name_cmdid)
-        .long(arg_name_cmdid)
-        .help("Command ID")
-        .takes_value(true);
+            let mut device = device_connect(restrictions_cmd, arg_name_sip, arg_name_dip, arg_name_token);
 
-    let matches = App::new("roborockutil")
-        .version("0.1.0")
-        .author("Bogdan Olar <olar.bogdan.dev@gmail.com>")
-        .about("RoboRock S5 utility")
-        .arg(sip_arg.clone()
-            .required(false))
-        .subcommand(SubCommand::with_name(arg_cmd_name_discover)
-            .about("Discover miio devices")
-            .arg(sip_arg.clone()
-                .required(true))
-            .arg(dip_arg.clone()
-                .required(false)))
-        .subcommand(SubCommand::with_name(arg_cmd_name_status)
-            .about("Get device information")
-            .arg(sip_arg.clone()
-                .required(true))
-            .arg(dip_arg.clone()
-                .required(true))
-            .arg(did_arg.clone()
-                .required(true))
-            .arg(token_arg.clone()
-                .required(true))
-            .arg(stamp_arg.clone()
-                .required(true))
-            .arg(cmdid_arg.clone()))
-        .subcommand(SubCommand::with_name(arg_cmd_name_info)
-            .about("Get device status")
-            .arg(sip_arg.clone()
-                .required(true))
-            .arg(dip_arg.clone()
-                .required(true))
-            .arg(did_arg.clone()
-                .required(true))
-            .arg(token_arg.clone()
-                .required(true))
-            .arg(cmdid_arg.clone()))
-        .get_matches();
+            if restrictions_cmd.is_present(arg_name_lab_mode) {
+                if let Err(e) = device.set_lab_status(true) {
+                    eprintln!("{}", e);
+                    process::exit(1);
+                }
+            }
+            match device.save_restrictions(&restrictions) {
+                Ok(()) => { println!("ok"); }
+                Err(e) => { eprintln!("{}", e); }
+            }
+        }
+    }
 
-    if let Some(discover_cmd) = matches.subcommand_matches(arg_cmd_name_discover) {
-        // process required arguments
-        let sip = arg_get_ip(arg_name_sip, &discover_cmd).unwrap_or_else(|e|  {
+    if let Some(maps_cmd) = matches.subcommand_matches(arg_cmd_name_maps) {
+        let mut device = device_connect(maps_cmd, arg_name_sip, arg_name_dip, arg_name_token);
+
+        let maps = device.multi_maps().unwrap_or_else(|e| {
             eprintln!("{}", e);
             process::exit(1);
         });
 
+        if let Some(name) = maps_cmd.value_of(arg_name_map) {
+            let map_flag = match maps.find(name) {
+                Some(map_info) => map_info.map_flag,
+                None => {
+                    eprintln!("Unknown map {}", name);
+                    process::exit(1);
+                }
+            };
+            match device.load_multi_map(map_flag) {
+                Ok(()) => { println!("ok"); }
+                Err(e) => { eprintln!("{}", e); }
+            }
+        } else {
+            println!("{} of {} maps saved", maps.multi_map_count, maps.max_multi_map);
+            for map_info in &maps.map_info {
+                println!("\t--map {}\t{}\t(saved {})", map_info.map_flag, map_info.name,
+                         format_timestamp(map_info.add_time));
+            }
+        }
+    }
+
+    if let Some(carpet_cmd) = matches.subcommand_matches(arg_cmd_name_carpet) {
         // process optional arguments
-        let dip_opt;
-        match arg_get_ip(arg_name_dip, &discover_cmd) {
-            Ok(ip) => dip_opt = Some(ip),
-            Err(_e) => dip_opt = None
+        let mut thresholds = [None; 4];
+        for (i, arg_name) in [arg_name_current_high, arg_name_current_low, arg_name_current_integral,
+                              arg_name_stall_time].iter().enumerate() {
+            if carpet_cmd.is_present(arg_name) {
+                thresholds[i] = Some(arg_get_value::<u32>(arg_name, &carpet_cmd).unwrap_or_else(|e| {
+                    eprintln!("{}", e);
+                    process::exit(1);
+                }));
+            }
         }
+        let clean_mode_opt = match carpet_cmd.value_of(arg_name_clean_mode) {
+            Some(_) => Some(arg_get_value::<CarpetCleanMode>(arg_name_clean_mode, &carpet_cmd).unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                process::exit(1);
+            })),
+            None => None
+        };
+        let boost_opt = carpet_cmd.value_of(arg_name_boost).map(|b| b == "on");
 
-        // create UDP socket
-        let socket = UdpSocket::bind(sip.to_string() + ":" + MI_DISCOVER_UDP_PORT.to_string().as_str())
-            .unwrap_or_else(|e|  {
+        let mut device = device_connect(carpet_cmd, arg_name_sip, arg_name_dip, arg_name_token);
+
+        if boost_opt.is_some() || thresholds.iter().any(|t| t.is_some()) {
+            let mut mode = device.carpet_mode().unwrap_or_else(|e| {
                 eprintln!("{}", e);
                 process::exit(1);
             });
+            mode.enabled = boost_opt.unwrap_or(mode.enabled);
+            mode.current_high = thresholds[0].unwrap_or(mode.current_high);
+            mode.current_low = thresholds[1].unwrap_or(mode.current_low);
+            mode.current_integral = thresholds[2].unwrap_or(mode.current_integral);
+            mode.stall_time = thresholds[3].unwrap_or(mode.stall_time);
 
-        // do discovery
-        match discovery::discover(socket, dip_opt) {
-            Ok(responses) => { print_discover_results(&responses); }
+            if let Err(e) = device.set_carpet_mode(&mode) {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+        }
+        if let Some(clean_mode) = clean_mode_opt {
+            if let Err(e) = device.set_carpet_clean_mode(clean_mode) {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+        }
+
+        match device.carpet_mode() {
+            Ok(mode) => { println!("Carpet mode: {}", mode); }
+            Err(e) => { eprintln!("{}", e); }
+        }
+        match device.carpet_clean_mode() {
+            Ok(clean_mode) => { println!("Carpet clean mode: {}", clean_mode); }
             Err(e) => { eprintln!("{}", e); }
         }
     }
+}
 
-    if let Some(status_cmd) = matches.subcommand_matches(arg_cmd_name_status) {
-        // process required arguments
-        let sip = arg_get_ip(arg_name_sip, &status_cmd).unwrap_or_else(|e| {
+/// Drive the robot with keys read from stdin, until `q` is entered or stdin is closed.
+///
+/// # Arguments
+///
+/// `device` - session with the device
+/// `velocity` - velocity when driving forward or backward, in m/s
+/// `rotation` - rotation when turning left or right, in degrees
+///
+fn drive(device: &mut Device, velocity: f64, rotation: f64) -> Result<(), deviceinfo::Error> {
+    let duration = Duration::from_millis(DRIVE_MOVE_MILLIS);
+
+    device.rc_start()?;
+    println!("w/s: forward/backward, a/d: turn left/right, q: quit");
+
+    let stdin = io::stdin();
+    'keys: for line in stdin.lock().lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_e) => break
+        };
+        for key in line.chars() {
+            let resp = match key {
+                'w' => device.rc_move(0.0, velocity, duration),
+                's' => device.rc_move(0.0, -velocity, duration),
+                'a' => device.rc_move(rotation, 0.0, duration),
+                'd' => device.rc_move(-rotation, 0.0, duration),
+                'q' => break 'keys,
+                _ => continue
+            };
+            if let Err(e) = resp {
+                // stop the remote control session before reporting the error
+                let _ = device.rc_end();
+                return Err(e);
+            }
+            // wait for the movement to finish before sending the next one
+            thread::sleep(duration);
+        }
+    }
+
+    device.rc_end()
+}
+
+/// Return the URL and the MD5 checksum of a package which the robot should download. If a local file is given,
+/// then it is served over HTTP, and the server is returned along with the URL.
+///
+/// # Arguments
+///
+/// `device_matches` - the matches of the subcommand which has the local IP argument
+/// `arg_matches` - the matches of the subcommand which has the package arguments
+///
+fn package_source(device_matches: &ArgMatches, arg_matches: &ArgMatches, arg_name_sip: &str, arg_name_url: &str,
+                  arg_name_file: &str, arg_name_md5: &str, arg_name_port: &str)
+                  -> (Option<FileServer>, String, String) {
+    let md5_opt = arg_matches.value_of(arg_name_md5).map(|md5| md5.to_lowercase());
+
+    if let Some(file) = arg_matches.value_of(arg_name_file) {
+        let sip = arg_get_ip(arg_name_sip, device_matches).unwrap_or_else(|e| {
             eprintln!("{}", e);
             process::exit(1);
         });
-        let dip = arg_get_ip(arg_name_dip, &status_cmd).unwrap_or_else(|e| {
+        let port = arg_get_value::<u16>(arg_name_port, arg_matches).unwrap_or_else(|e| {
             eprintln!("{}", e);
             process::exit(1);
         });
-        let did = arg_get_u32(arg_name_did, &status_cmd).unwrap_or_else(|e| {
-            eprintln!("{}", e);
+        let md5 = fileserver::md5_hex(Path::new(file)).unwrap_or_else(|e| {
+            eprintln!("{}: {}", file, e);
             process::exit(1);
         });
-        let token = arg_get_token(arg_name_token, &status_cmd).unwrap_or_else(|e| {
+        if let Some(expected) = md5_opt {
+            if expected != md5 {
+                eprintln!("{}: MD5 checksum is {}, expected {}", file, md5, expected);
+                process::exit(1);
+            }
+        }
+        let server = FileServer::serve(Path::new(file), sip, port).unwrap_or_else(|e| {
             eprintln!("{}", e);
             process::exit(1);
         });
-        let mut stamp = arg_get_u32(arg_name_stamp, &status_cmd).unwrap_or_else(|e| {
-            eprintln!("{}", e);
+        let url = server.url();
+        (Some(server), url, md5)
+    } else {
+        let url = arg_matches.value_of(arg_name_url).unwrap_or_else(|| {
+            eprintln!("{}", ArgError::NotFound(arg_name_url.to_string()));
             process::exit(1);
         });
-        let cmdid = arg_get_u32(arg_name_cmdid, &status_cmd).unwrap_or_else(|e| {
-            eprintln!("{}", e);
+        let md5 = md5_opt.unwrap_or_else(|| {
+            eprintln!("{}", ArgError::NotFound(arg_name_md5.to_string()));
             process::exit(1);
         });
+        (None, url.to_string(), md5)
+    }
+}
 
-        // create UDP socket
-        let socket = UdpSocket::bind(sip.to_string() + ":" + MI_DISCOVER_UDP_PORT.to_string().as_str())
-            .unwrap_or_else(|e|  {
-            eprintln!("{}", e);
-            process::exit(1);
-        });
+/// Poll the progress of a voice pack installation until it ends, and return the final state and progress
+fn wait_sound_install(device: &mut Device) -> Result<(SoundInstallState, SoundProgress), deviceinfo::Error> {
+    let mut tracker = SoundInstallTracker::default();
+    let mut waited = Duration::from_secs(0);
+    loop {
+        thread::sleep(Duration::from_millis(INSTALL_POLL_MILLIS));
+        waited += Duration::from_millis(INSTALL_POLL_MILLIS);
 
-        // get device status
-        let resp = deviceinfo::status(&socket, dip, did, &token, &mut stamp, cmdid);
-        println!("{:?}", resp);
+        let progress = device.sound_progress()?;
+        println!("{}: {}%", progress.state(), progress.progress);
+        if let Some(state) = tracker.update(progress.state()) {
+            return Ok((state, progress));
+        }
+        if waited.as_secs() >= SOUND_INSTALL_TIMEOUT_SECS {
+            return Err(deviceinfo::Error::Timeout(format!("voice pack installation did not end within {} s",
+                                                          SOUND_INSTALL_TIMEOUT_SECS)));
+        }
     }
+}
 
-    if let Some(info_cmd) = matches.subcommand_matches(arg_cmd_name_info) {
-        // process required arguments
-        let sip = arg_get_ip(arg_name_sip, &info_cmd).unwrap_or_else(|e| {
-            eprintln!("{}", e);
-            process::exit(1);
-        });
-        let dip = arg_get_ip(arg_name_dip, &info_cmd).unwrap_or_else(|e| {
-            eprintln!("{}", e);
-            process::exit(1);
-        });
-        let did = arg_get_u32(arg_name_did, &info_cmd).unwrap_or_else(|e| {
-            eprintln!("{}", e);
-            process::exit(1);
-        });
-        let token = arg_get_token(arg_name_token, &info_cmd).unwrap_or_else(|e| {
-            eprintln!("{}", e);
-            process::exit(1);
-        });
-        let cmdid = arg_get_u32(arg_name_cmdid, &info_cmd).unwrap_or_else(|e| {
-            eprintln!("{}", e);
-            process::exit(1);
-        });
+/// Poll the state of a firmware update until it ends, and return the final state (see `OtaTracker::update`)
+fn wait_ota(device: &mut Device) -> Result<OtaState, deviceinfo::Error> {
+    let mut tracker = OtaTracker::default();
+    let mut waited = Duration::from_secs(0);
+    loop {
+        thread::sleep(Duration::from_millis(INSTALL_POLL_MILLIS));
+        waited += Duration::from_millis(INSTALL_POLL_MILLIS);
+        if waited.as_secs() >= OTA_TIMEOUT_SECS {
+            return Err(deviceinfo::Error::Timeout(format!("firmware update did not end within {} s",
+                                                          OTA_TIMEOUT_SECS)));
+        }
 
-        // create UDP socket
-        let socket = UdpSocket::bind(sip.to_string() + ":" + MI_DISCOVER_UDP_PORT.to_string().as_str())
-            .unwrap_or_else(|e|  {
-                eprintln!("{}", e);
-                process::exit(1);
-            });
+        // the robot does not respond while it restarts to install the firmware, and starts a new session (with a new
+        // stamp) once it is back up, so the handshake has to be done again. Packets garbled while it goes down or
+        // comes back up are handled the same way.
+        let state = match device.ota_state() {
+            Ok(state) => state,
+            Err(deviceinfo::Error::NoResponse) | Err(deviceinfo::Error::Packet(_)) => {
+                match device.reconnect() {
+                    Ok(()) | Err(deviceinfo::Error::NoResponse) | Err(deviceinfo::Error::Packet(_)) => { continue; }
+                    Err(e) => { return Err(e); }
+                }
+            }
+            Err(e) => { return Err(e); }
+        };
+        let progress = device.ota_progress().unwrap_or(0);
+        println!("{}: {}%", state, progress);
+        if let Some(state) = tracker.update(state, progress) {
+            return Ok(state);
+        }
+    }
+}
 
-        // get device status
-        deviceinfo::info(&socket, dip, did, &token, cmdid);
+/// Print a summary of a map
+fn print_map(rrmap: &RRMap) {
+    println!("Map: index {}, sequence {}, version {}.{}", rrmap.header.map_index, rrmap.header.map_sequence,
+             rrmap.header.major_version, rrmap.header.minor_version);
+    if let Some(image) = &rrmap.image {
+        println!("Image: {}x{} pixels at ({}, {}), segments {:?}", image.width, image.height, image.left, image.top,
+                 image.segments());
+    }
+    if let Some(charger) = rrmap.charger {
+        println!("Charger: ({}, {})", charger.x, charger.y);
+    }
+    if let Some(robot) = rrmap.robot_position {
+        println!("Robot: ({}, {}), heading {}°", robot.x, robot.y, rrmap.robot_angle.unwrap_or(0));
+    }
+    if let Some(path) = &rrmap.path {
+        println!("Path: {} points", path.points.len());
+    }
+    if let Some(target) = rrmap.goto_target {
+        println!("Go-to target: ({}, {})", target.x, target.y);
+    }
+    for zone in &rrmap.cleaned_zones {
+        println!("Cleaned zone: {},{},{},{}", zone.x1, zone.y1, zone.x2, zone.y2);
+    }
+    if !rrmap.cleaned_segments.is_empty() {
+        println!("Cleaned segments: {:?}", rrmap.cleaned_segments);
+    }
+    for area in &rrmap.no_go_areas {
+        println!("No-go area: {:?}", area.iter().map(|p| (p.x, p.y)).collect::<Vec<(u32, u32)>>());
+    }
+    for area in &rrmap.no_mop_areas {
+        println!("No-mop area: {:?}", area.iter().map(|p| (p.x, p.y)).collect::<Vec<(u32, u32)>>());
+    }
+    for wall in &rrmap.virtual_walls {
+        println!("Virtual wall: ({}, {}) - ({}, {})", wall.start.x, wall.start.y, wall.end.x, wall.end.y);
     }
 }
 
+/// Open a UDP socket on the `--sip` interface and perform the hello handshake with the `--dip` device.
+///
+/// Exits the process if any of the arguments are missing or invalid, or if the device does not respond.
+///
+fn device_connect(arg_matches: &ArgMatches, arg_name_sip: &str, arg_name_dip: &str, arg_name_token: &str) -> Device {
+    let sip = arg_get_ip(arg_name_sip, arg_matches).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(1);
+    });
+    let dip = arg_get_ip(arg_name_dip, arg_matches).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(1);
+    });
+    let token = arg_get_token(arg_name_token, arg_matches).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(1);
+    });
+
+    // create UDP socket
+    let socket = UdpSocket::bind(sip.to_string() + ":" + MI_DISCOVER_UDP_PORT.to_string().as_str())
+        .unwrap_or_else(|e|  {
+            eprintln!("{}", e);
+            process::exit(1);
+        });
+
+    Device::connect(socket, dip, &token).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(1);
+    })
+}
 
 fn arg_get_ip(arg_name_str: &str, arg_matches: &ArgMatches) -> Result<Ipv4Addr, ArgError> {
     if let Some(ip_str) = arg_matches.value_of(arg_name_str) {
@@ -208,9 +1712,22 @@ fn arg_get_ip(arg_name_str: &str, arg_matches: &ArgMatches) -> Result<Ipv4Addr,
     }
 }
 
-fn arg_get_u32(arg_name_str: &str, arg_matches: &ArgMatches) -> Result<u32, ArgError> {
+/// Parse an argument value with `FromStr`, e.g. a number or a preset name
+fn arg_get_value<T: FromStr>(arg_name_str: &str, arg_matches: &ArgMatches) -> Result<T, ArgError> {
+    if let Some(val_str) = arg_matches.value_of(arg_name_str) {
+        if let Ok(val) = val_str.to_string().parse::<T>() {
+            return Ok(val);
+        } else {
+            Err(ArgError::Parse(arg_name_str.to_string(), val_str.to_string()))
+        }
+    } else {
+        Err(ArgError::NotFound(arg_name_str.to_string()))
+    }
+}
+
+fn arg_get_json(arg_name_str: &str, arg_matches: &ArgMatches) -> Result<Value, ArgError> {
     if let Some(val_str) = arg_matches.value_of(arg_name_str) {
-        if let Ok(val) = val_str.to_string().parse::<u32>() {
+        if let Ok(val) = serde_json::from_str(val_str) {
             return Ok(val);
         } else {
             Err(ArgError::Parse(arg_name_str.to_string(), val_str.to_string()))
@@ -243,8 +1760,6 @@ fn arg_get_token(arg_name_str: &str, arg_matches: &ArgMatches) -> Result<[u8; 16
 ///
 /// For each of the responses, the content is:
 ///     - device IP (`--dip`)
-///         - device ID (`--did`)
-///         - the message stamp (`--stamp`)
 ///         - the provisioning token (`--token`), which is a 16 character alphanumeric string, or the `INVALID` string
 ///           if the token is invalid
 ///
@@ -255,8 +1770,6 @@ fn arg_get_token(arg_name_str: &str, arg_matches: &ArgMatches) -> Result<[u8; 16
 fn print_discover_results(responses: &Vec<discovery::Response>) {
     for r in responses {
         println!("\t--dip {}", r.ip.to_string());
-        println!("\t\t--did {}", r.packet.device_id);
-        println!("\t\t--stamp {}", r.packet.stamp);
 
         if let Ok(token_str) = from_utf8(&r.packet.md5) {
             if token_str.chars().all(char::is_alphanumeric)
@@ -271,6 +1784,96 @@ fn print_discover_results(responses: &Vec<discovery::Response>) {
     }
 }
 
+/// Prints the content of a `get_status` response.
+///
+/// # Arguments
+///
+/// `resp` - the `get_status` response
+///
+fn print_status(resp: &StatusResponse) {
+    for r in &resp.result {
+        println!("State: {} ({})", r.state(), r.state_code());
+        if r.error().is_error() {
+            println!("Error: {} ({})", r.error(), r.error_code());
+            println!("\t{}", r.error().remedy());
+        }
+        println!("Battery: {}%", r.battery());
+        println!("Fan speed: {} ({})", r.fan_speed(), r.fan_power());
+        println!("Clean time: {} min", r.clean_time().as_secs() / 60);
+        println!("Clean area: {:.2} m²", r.clean_area());
+        println!("Map present: {}", r.map_present());
+        println!("Do-Not-Disturb: {}", r.dnd_enabled());
+        if let Some(clean_percent) = r.clean_percent() {
+            println!("Clean progress: {}%", clean_percent);
+        }
+        if let Some(water_box_attached) = r.water_box_attached() {
+            println!("Water box attached: {}", water_box_attached);
+        }
+        for (name, value) in r.unknown_fields() {
+            println!("{}: {}", name, value);
+        }
+    }
+}
+
+/// Prints the usage of the consumables.
+///
+/// For each consumable, the content is the remaining life in percent and in hours, and the time it was used.
+///
+/// # Arguments
+///
+/// `consumables` - the `get_consumable` result
+///
+fn print_consumables(consumables: &ConsumableStatus) {
+    for c in Consumable::ALL.iter() {
+        match (consumables.used(*c), consumables.remaining(*c), consumables.remaining_percent(*c)) {
+            (Some(used), Some(remaining), Some(percent)) if c.is_counted() => {
+                println!("{}: {:.0}% ({} uses left, used {} times)", c, percent, remaining, used);
+            }
+            (Some(used), Some(remaining), Some(percent)) => {
+                println!("{}: {:.0}% ({} h left, used {} h)", c, percent, remaining / 3600, used / 3600);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Prints a clean record on a single line: start time (UTC), duration, area, and how the cleaning ended
+///
+/// # Arguments
+///
+/// `record` - the clean record
+///
+fn print_clean_record(record: &CleanRecord) {
+    let outcome = if record.error().is_error() {
+        record.error().to_string()
+    } else if record.complete {
+        "completed".to_string()
+    } else {
+        "not completed".to_string()
+    };
+    println!("\t{}  {:>4} min  {:>7.2} m²  {}", format_timestamp(record.begin), record.duration().as_secs() / 60,
+             record.area(), outcome);
+}
+
+/// Format a UNIX timestamp as a UTC date and time, e.g. `2021-05-03 09:15:12`
+fn format_timestamp(timestamp: u64) -> String {
+    let days = (timestamp / 86400) as i64;
+    let secs = timestamp % 86400;
+
+    // convert the number of days since 1970-01-01 to a civil date
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02}", year, month, day, secs / 3600, (secs % 3600) / 60, secs % 60)
+}
+
 impl StdError for ArgError {
     fn description(&self) -> &str {
         match &*self {
@@ -301,4 +1904,12 @@ mod tests {
     {
 
     }
+
+    #[test]
+    fn test_format_timestamp()
+    {
+        assert_eq!(format_timestamp(0), "1970-01-01 00:00:00");
+        assert_eq!(format_timestamp(1488347071), "2017-03-01 05:44:31");
+        assert_eq!(format_timestamp(1620033312), "2021-05-03 09:15:12");
+    }
 }
\ No newline at end of file
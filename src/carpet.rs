@@ -0,0 +1,199 @@
+//! Carpet handling of the vacuum robot.
+//!
+//! The carpet mode (`get_carpet_mode` / `set_carpet_mode`) controls the suction boost on carpets: the robot detects
+//! a carpet from the current drawn by the main brush motor, and raises the fan speed while it is on one. The carpet
+//! clean mode (`get_carpet_clean_mode` / `set_carpet_clean_mode`) controls what a robot with a mop attached does
+//! when it meets a carpet.
+
+use serde::{Serialize, Deserialize};
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "CarpetModeRepr", into = "CarpetModeRepr")]
+pub struct CarpetMode {
+    /// Raise the fan speed on carpets
+    pub enabled: bool,
+    /// Integral of the brush motor current above which a carpet is detected
+    pub current_integral: u32,
+    /// Brush motor current above which a carpet is detected
+    pub current_high: u32,
+    /// Brush motor current below which the robot is no longer on a carpet
+    pub current_low: u32,
+    /// Seconds to keep the boost after the carpet is no longer detected
+    pub stall_time: u32,
+}
+
+/// The `get_carpet_mode` representation of the carpet mode
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct CarpetModeRepr {
+    enable: i32,
+    current_integral: u32,
+    current_high: u32,
+    current_low: u32,
+    stall_time: u32,
+}
+
+impl Default for CarpetMode {
+    /// The factory settings
+    fn default() -> CarpetMode {
+        CarpetMode { enabled: true, current_integral: 450, current_high: 500, current_low: 400, stall_time: 10 }
+    }
+}
+
+impl CarpetMode {
+    /// Check that the thresholds are consistent
+    pub fn validate(&self) -> Result<(), String> {
+        if self.current_low >= self.current_high {
+            return Err(format!("the low current ({}) must be lower than the high current ({})",
+                               self.current_low, self.current_high));
+        }
+        if self.stall_time == 0 {
+            return Err("the stall time must be at least 1 second".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl From<CarpetModeRepr> for CarpetMode {
+    fn from(r: CarpetModeRepr) -> CarpetMode {
+        CarpetMode {
+            enabled: r.enable != 0,
+            current_integral: r.current_integral,
+            current_high: r.current_high,
+            current_low: r.current_low,
+            stall_time: r.stall_time
+        }
+    }
+}
+
+impl From<CarpetMode> for CarpetModeRepr {
+    fn from(m: CarpetMode) -> CarpetModeRepr {
+        CarpetModeRepr {
+            enable: if m.enabled { 1 } else { 0 },
+            current_integral: m.current_integral,
+            current_high: m.current_high,
+            current_low: m.current_low,
+            stall_time: m.stall_time
+        }
+    }
+}
+
+impl fmt::Display for CarpetMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "boost {}, current high {}, low {}, integral {}, stall time {}s",
+               if self.enabled { "on" } else { "off" }, self.current_high, self.current_low,
+               self.current_integral, self.stall_time)
+    }
+}
+
+/// What the robot does when it meets a carpet while mopping
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "CarpetCleanModeRepr", into = "CarpetCleanModeRepr")]
+pub enum CarpetCleanMode {
+    /// Do not drive onto carpets
+    Avoid,
+    /// Lift the mop, and clean the carpet
+    Rise,
+    /// Clean the carpet without lifting the mop
+    Ignore,
+}
+
+/// The `get_carpet_clean_mode` representation of the carpet clean mode
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct CarpetCleanModeRepr {
+    carpet_clean_mode: u32,
+}
+
+impl CarpetCleanMode {
+    /// The numeric mode code used by the device
+    pub fn code(&self) -> u32 {
+        match *self {
+            CarpetCleanMode::Avoid => 0,
+            CarpetCleanMode::Rise => 1,
+            CarpetCleanMode::Ignore => 2,
+        }
+    }
+
+    /// Return the mode for a numeric mode code
+    pub fn from_code(code: u32) -> Option<CarpetCleanMode> {
+        match code {
+            0 => Some(CarpetCleanMode::Avoid),
+            1 => Some(CarpetCleanMode::Rise),
+            2 => Some(CarpetCleanMode::Ignore),
+            _ => None
+        }
+    }
+}
+
+impl std::convert::TryFrom<CarpetCleanModeRepr> for CarpetCleanMode {
+    type Error = String;
+
+    fn try_from(r: CarpetCleanModeRepr) -> Result<CarpetCleanMode, String> {
+        CarpetCleanMode::from_code(r.carpet_clean_mode)
+            .ok_or_else(|| format!("unknown carpet clean mode {}", r.carpet_clean_mode))
+    }
+}
+
+impl From<CarpetCleanMode> for CarpetCleanModeRepr {
+    fn from(m: CarpetCleanMode) -> CarpetCleanModeRepr {
+        CarpetCleanModeRepr { carpet_clean_mode: m.code() }
+    }
+}
+
+impl FromStr for CarpetCleanMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<CarpetCleanMode, String> {
+        match s.trim().to_lowercase().as_str() {
+            "avoid" => Ok(CarpetCleanMode::Avoid),
+            "rise" => Ok(CarpetCleanMode::Rise),
+            "ignore" => Ok(CarpetCleanMode::Ignore),
+            _ => Err(s.to_string())
+        }
+    }
+}
+
+impl fmt::Display for CarpetCleanMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CarpetCleanMode::Avoid => f.write_str("avoid"),
+            CarpetCleanMode::Rise => f.write_str("rise"),
+            CarpetCleanMode::Ignore => f.write_str("ignore"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_carpet_mode() {
+        let resp = json!([{"enable": 1, "current_integral": 450, "current_high": 500, "current_low": 400,
+                           "stall_time": 10}]);
+        let modes: Vec<CarpetMode> = serde_json::from_value(resp).unwrap();
+        assert_eq!(modes[0], CarpetMode::default());
+
+        let mode = CarpetMode { enabled: false, stall_time: 5, ..CarpetMode::default() };
+        assert_eq!(serde_json::to_string(&json!([mode])).unwrap(),
+                   "[{\"current_high\":500,\"current_integral\":450,\"current_low\":400,\"enable\":0,\"stall_time\":5}]");
+
+        assert!(mode.validate().is_ok());
+        assert!(CarpetMode { current_low: 500, ..mode }.validate().is_err());
+        assert!(CarpetMode { stall_time: 0, ..mode }.validate().is_err());
+    }
+
+    #[test]
+    fn test_carpet_clean_mode() {
+        let modes: Vec<CarpetCleanMode> = serde_json::from_value(json!([{"carpet_clean_mode": 1}])).unwrap();
+        assert_eq!(modes[0], CarpetCleanMode::Rise);
+        assert!(serde_json::from_value::<CarpetCleanMode>(json!({"carpet_clean_mode": 7})).is_err());
+
+        assert_eq!(serde_json::to_string(&CarpetCleanMode::Ignore).unwrap(), "{\"carpet_clean_mode\":2}");
+        assert_eq!("Avoid".parse::<CarpetCleanMode>(), Ok(CarpetCleanMode::Avoid));
+        assert!("lift".parse::<CarpetCleanMode>().is_err());
+        assert_eq!(CarpetCleanMode::Rise.to_string(), "rise");
+    }
+}
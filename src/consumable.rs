@@ -0,0 +1,99 @@
+//! The consumables of the vacuum robot, as reported by the `get_consumable` method, and reset with the
+//! `reset_consumable` method.
+
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Consumable {
+    MainBrush,
+    SideBrush,
+    Filter,
+    /// The cliff and wall sensors, which need to be wiped periodically
+    Sensor,
+    /// The water filter element, on robots which support mopping
+    FilterElement,
+    /// The strainer of the washing dock
+    Strainer,
+    /// The cleaning brush of the washing dock
+    CleaningBrush,
+    /// The dust bag of the auto-empty dock
+    DustCollection,
+}
+
+impl Consumable {
+    /// All the consumables which have a known replacement interval
+    pub const ALL: [Consumable; 8] = [Consumable::MainBrush, Consumable::SideBrush, Consumable::Filter,
+                                      Consumable::Sensor, Consumable::FilterElement, Consumable::Strainer,
+                                      Consumable::CleaningBrush, Consumable::DustCollection];
+
+    /// The name of the consumable, as used by the `get_consumable` and `reset_consumable` methods
+    pub fn key(&self) -> &'static str {
+        match *self {
+            Consumable::MainBrush => "main_brush_work_time",
+            Consumable::SideBrush => "side_brush_work_time",
+            Consumable::Filter => "filter_work_time",
+            Consumable::Sensor => "sensor_dirty_time",
+            Consumable::FilterElement => "filter_element_work_time",
+            Consumable::Strainer => "strainer_work_times",
+            Consumable::CleaningBrush => "cleaning_brush_work_times",
+            Consumable::DustCollection => "dust_collection_work_times",
+        }
+    }
+
+    /// Return `true` if the usage of the consumable is a number of uses (mop washes or bin emptyings), rather than a
+    /// work time in seconds
+    pub fn is_counted(&self) -> bool {
+        matches!(*self, Consumable::Strainer | Consumable::CleaningBrush | Consumable::DustCollection)
+    }
+
+    /// The usage after which the consumable should be replaced (or cleaned, for the sensors and the dock parts): a
+    /// work time in seconds, or a number of uses if `is_counted` is `true`
+    pub fn interval(&self) -> u64 {
+        match *self {
+            Consumable::MainBrush => 300 * 3600,
+            Consumable::SideBrush => 200 * 3600,
+            Consumable::Filter => 150 * 3600,
+            Consumable::Sensor => 30 * 3600,
+            Consumable::FilterElement => 300 * 3600,
+            Consumable::Strainer => 150,
+            Consumable::CleaningBrush => 300,
+            Consumable::DustCollection => 60,
+        }
+    }
+}
+
+impl fmt::Display for Consumable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Consumable::MainBrush => f.write_str("Main brush"),
+            Consumable::SideBrush => f.write_str("Side brush"),
+            Consumable::Filter => f.write_str("Filter"),
+            Consumable::Sensor => f.write_str("Sensors"),
+            Consumable::FilterElement => f.write_str("Water filter element"),
+            Consumable::Strainer => f.write_str("Dock strainer"),
+            Consumable::CleaningBrush => f.write_str("Dock cleaning brush"),
+            Consumable::DustCollection => f.write_str("Dust bag"),
+        }
+    }
+}
+
+impl FromStr for Consumable {
+    type Err = String;
+
+    /// Parse a consumable name: `main_brush`, `side_brush`, `filter`, `sensor`, `filter_element`, `strainer`,
+    /// `cleaning_brush` or `dust_collection`
+    fn from_str(s: &str) -> Result<Consumable, String> {
+        match s.to_lowercase().as_str() {
+            "main_brush" => Ok(Consumable::MainBrush),
+            "side_brush" => Ok(Consumable::SideBrush),
+            "filter" => Ok(Consumable::Filter),
+            "sensor" | "sensors" => Ok(Consumable::Sensor),
+            "filter_element" => Ok(Consumable::FilterElement),
+            "strainer" => Ok(Consumable::Strainer),
+            "cleaning_brush" => Ok(Consumable::CleaningBrush),
+            "dust_collection" | "dust_bag" => Ok(Consumable::DustCollection),
+            _ => Err(s.to_string())
+        }
+    }
+}
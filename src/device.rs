@@ -0,0 +1,732 @@
+//! Stateful session with a single miio device.
+//!
+//! A `Device` owns the UDP socket used to talk to the robot, and keeps track of everything which would otherwise
+//! need to be passed around with every command:
+//!     - the device ID and the device's stamp, both learned from the miio "hello" handshake
+//!     - the offset of the device's stamp against local time, so that each command is sent with a current stamp
+//!     - the command id, which is incremented for each command-response pair
+//!
+
+use crate::deviceinfo::{self, Error, Error::*};
+use crate::miiopayloads::*;
+use crate::fanspeed::{FanSpeed, FanSpeedScheme};
+use crate::consumable::Consumable;
+use crate::dnd::DndWindow;
+use crate::timer::{self, CleanTimer};
+use crate::sound::MAX_VOLUME;
+use crate::ota::{self, OtaState};
+use crate::restriction::Restrictions;
+use crate::carpet::{CarpetMode, CarpetCleanMode};
+use crate::zone::{Zone, validate_zones, MAX_COORDINATE, MAX_REPEATS};
+use std::net::{UdpSocket, IpAddr, Ipv4Addr};
+use std::io::ErrorKind;
+use std::time::{Duration, Instant};
+use std::thread;
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+use miiobin::{MI_DISCOVER_PACKET, MI_DISCOVER_UDP_PORT, MiPacket};
+
+/// How many milliseconds to wait for a response to the hello request
+const HELLO_TIMEOUT: Duration = Duration::from_millis(2000);
+
+/// The first command id used by a new session
+const FIRST_CMDID: u32 = 1;
+
+/// How many times to ask for the map while the robot is still uploading it
+const MAP_RETRIES: u32 = 5;
+
+/// How long to wait before asking for the map again
+const MAP_RETRY_INTERVAL: Duration = Duration::from_millis(1000);
+
+#[derive(Debug)]
+pub struct Device {
+    socket: UdpSocket,
+    ip: Ipv4Addr,
+    did: u32,
+    token: [u8; 16],
+    stamp: u32,
+    stamp_instant: Instant,
+    cmdid: u32,
+    rc_seqnum: u32,
+}
+
+impl Device {
+    /// Perform the miio hello handshake with a device, and return a session which can be used to send it commands
+    ///
+    /// # Arguments
+    ///
+    /// `socket` - UDP socket on which to communicate with the device. The session takes ownership of it.
+    /// `dip` - target device IP
+    /// `token` - encryption key
+    ///
+    pub fn connect(socket: UdpSocket, dip: Ipv4Addr, token: &[u8; 16]) -> Result<Device, Error> {
+        let hello_resp = hello(&socket, dip)?;
+
+        Ok(Device {
+            socket,
+            ip: dip,
+            did: hello_resp.device_id,
+            token: *token,
+            stamp: hello_resp.stamp,
+            stamp_instant: Instant::now(),
+            cmdid: FIRST_CMDID,
+            rc_seqnum: 0,
+        })
+    }
+
+    /// Perform the hello handshake again, e.g. after the device restarted, and start a new session on the same socket
+    pub fn reconnect(&mut self) -> Result<(), Error> {
+        let hello_resp = hello(&self.socket, self.ip)?;
+        self.start_session(&hello_resp);
+        Ok(())
+    }
+
+    /// The IP of the device
+    pub fn ip(&self) -> Ipv4Addr {
+        self.ip
+    }
+
+    /// The device ID, as reported in the hello handshake
+    pub fn did(&self) -> u32 {
+        self.did
+    }
+
+    /// The device's current stamp, extrapolated from the last stamp received from the device
+    pub fn stamp(&self) -> u32 {
+        self.stamp.wrapping_add(self.stamp_instant.elapsed().as_secs() as u32)
+    }
+
+    /// Return the device status
+    pub fn status(&mut self) -> Result<StatusResponse, Error> {
+        let cmdid = self.next_cmdid();
+        let mut stamp = self.stamp();
+        let resp = deviceinfo::status(&self.socket, self.ip, self.did, &self.token, &mut stamp, cmdid);
+        self.sync_stamp(stamp);
+        resp
+    }
+
+    /// Return the device information (model, firmware and hardware version, network configuration, etc.)
+    pub fn info(&mut self) -> Result<DeviceInfo, Error> {
+        let cmdid = self.next_cmdid();
+        let mut stamp = self.stamp();
+        let resp = deviceinfo::info(&self.socket, self.ip, self.did, &self.token, &mut stamp, cmdid);
+        self.sync_stamp(stamp);
+        resp
+    }
+
+    /// Send any miio method to the device, and return the `result` value of its response
+    ///
+    /// # Arguments
+    ///
+    /// `method` - name of the miio method, e.g. `get_status`, `miIO.info`, `app_start`
+    /// `params` - method parameters, usually a JSON array or object
+    ///
+    pub fn send_command(&mut self, method: &str, params: Value) -> Result<Value, Error> {
+        let cmdid = self.next_cmdid();
+        let mut stamp = self.stamp();
+        let resp = deviceinfo::send_command(&self.socket, self.ip, self.did, &self.token, &mut stamp, cmdid,
+                                            method, params);
+        self.sync_stamp(stamp);
+        resp
+    }
+
+    /// Send any miio method to the device, and deserialize the `result` value of its response into `T`
+    pub fn send_command_as<T: DeserializeOwned>(&mut self, method: &str, params: Value) -> Result<T, Error> {
+        let cmdid = self.next_cmdid();
+        let mut stamp = self.stamp();
+        let resp = deviceinfo::send_command_as(&self.socket, self.ip, self.did, &self.token, &mut stamp, cmdid,
+                                               method, params);
+        self.sync_stamp(stamp);
+        resp
+    }
+
+    /// Send a command which controls the cleaning, and check that the device acknowledged it
+    pub fn control(&mut self, command: ControlCommand) -> Result<(), Error> {
+        self.send_command_ok(command.method(), json!([]))
+    }
+
+    /// Start (or resume) cleaning
+    pub fn start(&mut self) -> Result<(), Error> {
+        self.control(ControlCommand::Start)
+    }
+
+    /// Pause cleaning
+    pub fn pause(&mut self) -> Result<(), Error> {
+        self.control(ControlCommand::Pause)
+    }
+
+    /// Stop cleaning
+    pub fn stop(&mut self) -> Result<(), Error> {
+        self.control(ControlCommand::Stop)
+    }
+
+    /// Return to the charging dock. The cleaning is paused first, since the robot ignores `app_charge` while it is
+    /// cleaning.
+    pub fn home(&mut self) -> Result<(), Error> {
+        self.control(ControlCommand::Pause)?;
+        self.control(ControlCommand::Home)
+    }
+
+    /// Clean the area around the current position
+    pub fn spot(&mut self) -> Result<(), Error> {
+        self.control(ControlCommand::Spot)
+    }
+
+    /// Play a sound, to help find the robot
+    pub fn locate(&mut self) -> Result<(), Error> {
+        self.control(ControlCommand::Locate)
+    }
+
+    /// Return the raw suction power, as reported by the robot
+    pub fn fan_power(&mut self) -> Result<i32, Error> {
+        self.send_command_first(METHOD_GET_CUSTOM_MODE, json!([]))
+    }
+
+    /// Return the suction power
+    pub fn fan_speed(&mut self) -> Result<FanSpeed, Error> {
+        Ok(FanSpeed::from(self.fan_power()?))
+    }
+
+    /// Set the raw suction power
+    pub fn set_fan_power(&mut self, fan_power: i32) -> Result<(), Error> {
+        self.send_command_ok(METHOD_SET_CUSTOM_MODE, json!([fan_power]))
+    }
+
+    /// Set the suction power
+    ///
+    /// # Arguments
+    ///
+    /// `speed` - the suction power preset
+    /// `scheme_opt` - the numeric scheme used by the firmware. If this argument is `Option::None`, then the scheme is
+    ///         detected from the current suction power of the robot.
+    ///
+    pub fn set_fan_speed(&mut self, speed: FanSpeed, scheme_opt: Option<FanSpeedScheme>) -> Result<(), Error> {
+        let scheme = match scheme_opt {
+            Some(scheme) => scheme,
+            None => self.fan_speed_scheme()?
+        };
+        self.set_fan_power(speed.fan_power(scheme))
+    }
+
+    /// Detect the numeric suction power scheme of the firmware, from the current suction power of the robot, and
+    /// from its model if the suction power is used by both percentage schemes
+    pub fn fan_speed_scheme(&mut self) -> Result<FanSpeedScheme, Error> {
+        match FanSpeedScheme::detect(self.fan_power()?) {
+            FanSpeedScheme::Percentage => {
+                let model = self.info()?.model;
+                Ok(FanSpeedScheme::from_model(&model).unwrap_or(FanSpeedScheme::Percentage))
+            }
+            scheme => Ok(scheme)
+        }
+    }
+
+    /// Return the usage of the consumables
+    pub fn consumables(&mut self) -> Result<ConsumableStatus, Error> {
+        self.send_command_first(METHOD_GET_CONSUMABLE, json!([]))
+    }
+
+    /// Reset the usage time of a consumable, e.g. after it was replaced
+    pub fn reset_consumable(&mut self, consumable: Consumable) -> Result<(), Error> {
+        self.send_command_ok(METHOD_RESET_CONSUMABLE, json!([consumable.key()]))
+    }
+
+    /// Return the cleaning totals, and the ids of the most recent clean records
+    pub fn clean_summary(&mut self) -> Result<CleanSummary, Error> {
+        self.send_command_as(METHOD_GET_CLEAN_SUMMARY, json!([]))
+    }
+
+    /// Return a single clean record
+    ///
+    /// # Arguments
+    ///
+    /// `id` - the id of the record, as listed in the `CleanSummary`
+    ///
+    pub fn clean_record(&mut self, id: u64) -> Result<CleanRecord, Error> {
+        self.send_command_first(METHOD_GET_CLEAN_RECORD, json!([id]))
+    }
+
+    /// Clean one or more rectangular zones
+    ///
+    /// # Arguments
+    ///
+    /// `zones` - the zones to clean, at most `zone::MAX_ZONES`
+    ///
+    pub fn zoned_clean(&mut self, zones: &[Zone]) -> Result<(), Error> {
+        if let Err(e) = validate_zones(zones) { return Err(InvalidArgument(e)); }
+        let params: Vec<[u32; 5]> = zones.iter().map(|z| z.to_params()).collect();
+        self.send_command_ok(METHOD_APP_ZONED_CLEAN, json!(params))
+    }
+
+    /// Drive to a point of the map
+    ///
+    /// # Arguments
+    ///
+    /// `x`, `y` - the target point, in robot coordinates (mm)
+    ///
+    pub fn goto(&mut self, x: u32, y: u32) -> Result<(), Error> {
+        if x > MAX_COORDINATE || y > MAX_COORDINATE {
+            return Err(InvalidArgument(format!("target ({}, {}) is outside of the map (0..{})", x, y,
+                                               MAX_COORDINATE)));
+        }
+        self.send_command_ok(METHOD_APP_GOTO_TARGET, json!([x, y]))
+    }
+
+    /// Start a remote control session
+    pub fn rc_start(&mut self) -> Result<(), Error> {
+        self.rc_seqnum = 0;
+        self.send_command_ok(METHOD_APP_RC_START, json!([]))
+    }
+
+    /// Move the robot during a remote control session. Each movement is numbered automatically.
+    ///
+    /// # Arguments
+    ///
+    /// `rotation` - rotation in degrees, between `-RC_MAX_ROTATION` and `RC_MAX_ROTATION`
+    /// `velocity` - velocity in m/s, between `-RC_MAX_VELOCITY` and `RC_MAX_VELOCITY`
+    /// `duration` - duration of the movement, at most `RC_MAX_DURATION` milliseconds
+    ///
+    pub fn rc_move(&mut self, rotation: f64, velocity: f64, duration: Duration) -> Result<(), Error> {
+        let rc_move = match RcMove::with_duration(rotation, velocity, duration, self.rc_seqnum + 1) {
+            Ok(rc_move) => rc_move,
+            Err(e) => { return Err(InvalidArgument(e)); }
+        };
+        self.rc_seqnum = rc_move.seqnum;
+        self.send_command_ok(METHOD_APP_RC_MOVE, json!([rc_move]))
+    }
+
+    /// End a remote control session
+    pub fn rc_end(&mut self) -> Result<(), Error> {
+        self.send_command_ok(METHOD_APP_RC_END, json!([]))
+    }
+
+    /// Return the segments (rooms) of the current map, and their cloud room ids
+    pub fn room_mapping(&mut self) -> Result<Vec<RoomMapping>, Error> {
+        self.send_command_as(METHOD_GET_ROOM_MAPPING, json!([]))
+    }
+
+    /// Clean one or more segments (rooms)
+    ///
+    /// # Arguments
+    ///
+    /// `segments` - the ids of the segments to clean, as reported by `room_mapping`
+    /// `repeats` - how many times to clean each segment, between `1` and `zone::MAX_REPEATS`
+    ///
+    pub fn segment_clean(&mut self, segments: &[u32], repeats: u32) -> Result<(), Error> {
+        if segments.is_empty() {
+            return Err(InvalidArgument("no segments given".to_string()));
+        }
+        if repeats < 1 || repeats > MAX_REPEATS {
+            return Err(InvalidArgument(format!("repeat count must be between 1 and {}", MAX_REPEATS)));
+        }
+
+        // older firmware only accepts the list of segments, so the object form is used only when needed
+        let params = if repeats == 1 {
+            json!(segments)
+        } else {
+            json!([SegmentClean { segments: segments.to_vec(), repeat: repeats }])
+        };
+        self.send_command_ok(METHOD_APP_SEGMENT_CLEAN, params)
+    }
+
+    /// Return the Do-Not-Disturb window
+    pub fn dnd_window(&mut self) -> Result<DndWindow, Error> {
+        self.send_command_first(METHOD_GET_DND_TIMER, json!([]))
+    }
+
+    /// Set and enable the Do-Not-Disturb window
+    ///
+    /// # Arguments
+    ///
+    /// `window` - the start and end of the window. The `enabled` flag is ignored.
+    ///
+    pub fn set_dnd_window(&mut self, window: DndWindow) -> Result<(), Error> {
+        if let Err(e) = window.validate() {
+            return Err(InvalidArgument(e));
+        }
+        self.send_command_ok(METHOD_SET_DND_TIMER, json!(window.to_params()))
+    }
+
+    /// Disable the Do-Not-Disturb window
+    pub fn disable_dnd(&mut self) -> Result<(), Error> {
+        self.send_command_ok(METHOD_CLOSE_DND_TIMER, json!([]))
+    }
+
+    /// Return the cleaning timers stored on the device. A timer which cannot be parsed is returned as an error
+    /// message, and does not prevent the other timers from being returned.
+    pub fn timers(&mut self) -> Result<Vec<Result<CleanTimer, String>>, Error> {
+        let timers: Vec<Value> = self.send_command_as(METHOD_GET_TIMER, json!([]))?;
+        Ok(timer::parse_timers(timers))
+    }
+
+    /// Add a cleaning timer. The timer is enabled by the device when it is added.
+    pub fn add_timer(&mut self, timer: &CleanTimer) -> Result<(), Error> {
+        if let Err(e) = timer.validate() {
+            return Err(InvalidArgument(e));
+        }
+        self.send_command_ok(METHOD_SET_TIMER, timer.to_params())
+    }
+
+    /// Enable or disable a cleaning timer
+    ///
+    /// # Arguments
+    ///
+    /// `id` - the id of the timer
+    /// `enabled` - whether the timer should run
+    ///
+    pub fn set_timer_enabled(&mut self, id: &str, enabled: bool) -> Result<(), Error> {
+        self.send_command_ok(METHOD_UPD_TIMER, json!([id, if enabled { "on" } else { "off" }]))
+    }
+
+    /// Delete a cleaning timer
+    pub fn delete_timer(&mut self, id: &str) -> Result<(), Error> {
+        self.send_command_ok(METHOD_DEL_TIMER, json!([id]))
+    }
+
+    /// Return the sound volume, between `0` and `sound::MAX_VOLUME`
+    pub fn sound_volume(&mut self) -> Result<u32, Error> {
+        self.send_command_first(METHOD_GET_SOUND_VOLUME, json!([]))
+    }
+
+    /// Set the sound volume
+    ///
+    /// # Arguments
+    ///
+    /// `volume` - the volume, between `0` and `sound::MAX_VOLUME`
+    ///
+    pub fn set_sound_volume(&mut self, volume: u32) -> Result<(), Error> {
+        if volume > MAX_VOLUME {
+            return Err(InvalidArgument(format!("volume must be between 0 and {}", MAX_VOLUME)));
+        }
+        self.send_command_ok(METHOD_CHANGE_SOUND_VOLUME, json!([volume]))
+    }
+
+    /// Play a test sound, at the current volume
+    pub fn test_sound_volume(&mut self) -> Result<(), Error> {
+        self.send_command_ok(METHOD_TEST_SOUND_VOLUME, json!([]))
+    }
+
+    /// Return the installed voice pack
+    pub fn current_sound(&mut self) -> Result<CurrentSound, Error> {
+        self.send_command_first(METHOD_GET_CURRENT_SOUND, json!([]))
+    }
+
+    /// Start downloading and installing a voice pack. The progress is reported by `sound_progress`.
+    pub fn install_sound(&mut self, install: &SoundInstall) -> Result<(), Error> {
+        if install.md5.len() != 32 || !install.md5.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(InvalidArgument(format!("invalid MD5 checksum {}", install.md5)));
+        }
+        // the reply is the initial progress of the installation, rather than `ok`
+        self.send_command(METHOD_DNLD_INSTALL_SOUND, json!(install))?;
+        Ok(())
+    }
+
+    /// Return the progress of a voice pack installation
+    pub fn sound_progress(&mut self) -> Result<SoundProgress, Error> {
+        self.send_command_first(METHOD_GET_SOUND_PROGRESS, json!([]))
+    }
+
+    /// Start a firmware update. The update is refused if the robot is not docked, or if its battery level is too low.
+    /// The progress is reported by `ota_state` and `ota_progress`.
+    ///
+    /// # Arguments
+    ///
+    /// `url` - URL of the firmware image, which must be reachable by the robot
+    /// `md5` - MD5 checksum of the firmware image
+    /// `min_battery` - the lowest battery level at which the update is started, in percent (see `ota::MIN_BATTERY`)
+    ///
+    pub fn ota_update(&mut self, url: &str, md5: &str, min_battery: u32) -> Result<(), Error> {
+        if md5.len() != 32 || !md5.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(InvalidArgument(format!("invalid MD5 checksum {}", md5)));
+        }
+        let mut status = self.status()?;
+        if status.result.is_empty() {
+            return Err(Unexpected("[]".to_string()));
+        }
+        if let Err(e) = ota::check_ready(&status.result.remove(0), min_battery) {
+            return Err(NotReady(e));
+        }
+
+        let request = OtaRequest::new(url, md5);
+        self.send_command_ok(METHOD_OTA, json!(request))
+    }
+
+    /// Return the state of the firmware update
+    pub fn ota_state(&mut self) -> Result<OtaState, Error> {
+        // depending on the firmware, the state is reported as `["idle"]` or as `"idle"`
+        let result = self.send_command(METHOD_GET_OTA_STATE, json!([]))?;
+        match result.as_array().and_then(|r| r.first()).unwrap_or(&result).as_str() {
+            Some(state) => Ok(OtaState::from(state)),
+            None => Err(Unexpected(result.to_string()))
+        }
+    }
+
+    /// Return the progress of the firmware update, in percent
+    pub fn ota_progress(&mut self) -> Result<u32, Error> {
+        let result = self.send_command(METHOD_GET_OTA_PROGRESS, json!([]))?;
+        match result.as_array().and_then(|r| r.first()).unwrap_or(&result).as_u64() {
+            Some(progress) => Ok(progress as u32),
+            None => Err(Unexpected(result.to_string()))
+        }
+    }
+
+    /// Make the robot upload its map, and return the URL of the uploaded map. The map can be parsed with `map::parse`.
+    pub fn map_url(&mut self) -> Result<String, Error> {
+        for _ in 0..MAP_RETRIES {
+            let result: Vec<String> = self.send_command_as(METHOD_GET_MAP_V1, json!([]))?;
+            match result.first().map(|r| r.as_str()) {
+                // the upload is still in progress
+                Some("retry") => { thread::sleep(MAP_RETRY_INTERVAL); }
+                Some(url) => { return Ok(url.to_string()); }
+                None => { return Err(Unexpected("[]".to_string())); }
+            }
+        }
+        Err(Unexpected("retry".to_string()))
+    }
+
+    /// Enable or disable the lab mode, which is needed by older firmware to keep the map (and its restrictions)
+    /// between cleanings
+    pub fn set_lab_status(&mut self, enabled: bool) -> Result<(), Error> {
+        self.send_command_ok(METHOD_SET_LAB_STATUS, json!([enabled as u8]))
+    }
+
+    /// Replace the no-go zones, no-mop zones and virtual walls of the current map
+    ///
+    /// # Arguments
+    ///
+    /// `restrictions` - the new restrictions. Restrictions which are not given are removed.
+    ///
+    pub fn save_restrictions(&mut self, restrictions: &Restrictions) -> Result<(), Error> {
+        if let Err(e) = restrictions.validate() {
+            return Err(InvalidArgument(e));
+        }
+        self.send_command_ok(METHOD_SAVE_MAP, restrictions.to_params())
+    }
+
+    /// Return the maps saved on the device, on firmware which supports multiple maps
+    pub fn multi_maps(&mut self) -> Result<MultiMapsList, Error> {
+        self.send_command_first(METHOD_GET_MULTI_MAPS_LIST, json!([]))
+    }
+
+    /// Make a saved map the current map, e.g. after moving the robot to another floor
+    ///
+    /// # Arguments
+    ///
+    /// `map_flag` - the id of the map, as listed by `multi_maps`
+    ///
+    pub fn load_multi_map(&mut self, map_flag: u32) -> Result<(), Error> {
+        let maps = self.multi_maps()?;
+        if !maps.map_info.iter().any(|m| m.map_flag == map_flag) {
+            return Err(InvalidArgument(format!("no saved map with flag {}", map_flag)));
+        }
+        self.send_command_ok(METHOD_LOAD_MULTI_MAP, json!([map_flag]))
+    }
+
+    /// Rename a segment (room) of the current map
+    ///
+    /// # Arguments
+    ///
+    /// `segment_id` - the id of the segment, as reported by `room_mapping`
+    /// `name` - the new name of the segment
+    ///
+    pub fn name_segment(&mut self, segment_id: u32, name: &str) -> Result<(), Error> {
+        if name.trim().is_empty() {
+            return Err(InvalidArgument("empty segment name".to_string()));
+        }
+        self.send_command_ok(METHOD_NAME_SEGMENT, json!([segment_id, name.trim()]))
+    }
+
+    /// Return the carpet mode (suction boost on carpets)
+    pub fn carpet_mode(&mut self) -> Result<CarpetMode, Error> {
+        self.send_command_first(METHOD_GET_CARPET_MODE, json!([]))
+    }
+
+    /// Set the carpet mode (suction boost on carpets)
+    ///
+    /// # Arguments
+    ///
+    /// `mode` - whether to boost, and the brush motor current thresholds used to detect carpets
+    ///
+    pub fn set_carpet_mode(&mut self, mode: &CarpetMode) -> Result<(), Error> {
+        if let Err(e) = mode.validate() {
+            return Err(InvalidArgument(e));
+        }
+        self.send_command_ok(METHOD_SET_CARPET_MODE, json!([mode]))
+    }
+
+    /// Return what the robot does with carpets while mopping
+    pub fn carpet_clean_mode(&mut self) -> Result<CarpetCleanMode, Error> {
+        self.send_command_first(METHOD_GET_CARPET_CLEAN_MODE, json!([]))
+    }
+
+    /// Set what the robot does with carpets while mopping
+    ///
+    /// # Arguments
+    ///
+    /// `mode` - avoid carpets, lift the mop on carpets, or ignore carpets
+    ///
+    pub fn set_carpet_clean_mode(&mut self, mode: CarpetCleanMode) -> Result<(), Error> {
+        self.send_command_ok(METHOD_SET_CARPET_CLEAN_MODE, json!(mode))
+    }
+
+    /// Send a command whose result is an array, and deserialize the first element of the array into `T`
+    fn send_command_first<T: DeserializeOwned>(&mut self, method: &str, params: Value) -> Result<T, Error> {
+        let mut result: Vec<T> = self.send_command_as(method, params)?;
+        if result.is_empty() {
+            Err(Unexpected("[]".to_string()))
+        } else {
+            Ok(result.remove(0))
+        }
+    }
+
+    /// Send a command, and check that the device acknowledged it with `ok`
+    fn send_command_ok(&mut self, method: &str, params: Value) -> Result<(), Error> {
+        let result = self.send_command(method, params)?;
+        if is_ok_result(&result) {
+            Ok(())
+        } else {
+            Err(Unexpected(result.to_string()))
+        }
+    }
+
+    /// Return the command id to be used for the next command, and advance the counter
+    fn next_cmdid(&mut self) -> u32 {
+        let cmdid = self.cmdid;
+        self.cmdid = self.cmdid.wrapping_add(1);
+        cmdid
+    }
+
+    /// Start a new session from a hello response: new device ID and stamp, and command ids starting over
+    fn start_session(&mut self, hello_resp: &MiPacket) {
+        self.did = hello_resp.device_id;
+        self.sync_stamp(hello_resp.stamp);
+        self.cmdid = FIRST_CMDID;
+        self.rc_seqnum = 0;
+    }
+
+    /// Re-synchronize the local time offset with the stamp received in the last device response
+    fn sync_stamp(&mut self, stamp: u32) {
+        self.stamp = stamp;
+        self.stamp_instant = Instant::now();
+    }
+}
+
+/// Send the miio hello packet to a device and return its (unencrypted) response, which contains the device ID and
+/// the device's current stamp.
+///
+/// # Arguments
+///
+/// `socket` - UDP socket on which to transmit the hello packet, and receive the response
+/// `dip` - target device IP
+///
+fn hello(socket: &UdpSocket, dip: Ipv4Addr) -> Result<MiPacket, Error> {
+    if let Err(e) = socket.send_to(&MI_DISCOVER_PACKET, dip.to_string() +
+        ":" + MI_DISCOVER_UDP_PORT.to_string().as_str()) {
+        return Err(Socket(e.to_string()));
+    }
+
+    // packets from other sources do not extend the wait
+    let deadline = Instant::now() + HELLO_TIMEOUT;
+    let mut comm_buf = [0u8;1024];
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining == Duration::from_secs(0) {
+            return Err(NoResponse);
+        }
+        if let Err(e) = socket.set_read_timeout(Option::Some(remaining)) { return Err(Socket(e.to_string())); }
+
+        match socket.recv_from(&mut comm_buf) {
+            Ok((amt, src)) => {
+                // ignore anything which does not come from the target device
+                if src.ip() != IpAddr::V4(dip) {
+                    continue;
+                }
+                return match MiPacket::parse(&comm_buf[..amt]) {
+                    Ok(packet) => Ok(packet),
+                    Err(e) => Err(Packet(e.to_string()))
+                };
+            }
+            Err(e) => {
+                return match e.kind() {
+                    ErrorKind::WouldBlock | ErrorKind::TimedOut => Err(NoResponse),
+                    _ => Err(Socket(e.to_string()))
+                };
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_device() -> Device {
+        Device {
+            socket: UdpSocket::bind("127.0.0.1:0").unwrap(),
+            ip: Ipv4Addr::LOCALHOST,
+            did: 0x0123_4567,
+            token: [0; 16],
+            stamp: 1000,
+            stamp_instant: Instant::now(),
+            cmdid: FIRST_CMDID,
+            rc_seqnum: 0,
+        }
+    }
+
+    #[test]
+    fn test_stamp() {
+        let mut device = test_device();
+        device.stamp_instant = Instant::now() - Duration::from_secs(5);
+        assert_eq!(device.stamp(), 1005);
+
+        device.stamp = u32::MAX - 1;
+        assert_eq!(device.stamp(), 3);
+
+        device.sync_stamp(2000);
+        assert_eq!(device.stamp(), 2000);
+    }
+
+    #[test]
+    fn test_next_cmdid() {
+        let mut device = test_device();
+        assert_eq!(device.next_cmdid(), FIRST_CMDID);
+        assert_eq!(device.next_cmdid(), FIRST_CMDID + 1);
+
+        device.cmdid = u32::MAX;
+        assert_eq!(device.next_cmdid(), u32::MAX);
+        assert_eq!(device.next_cmdid(), 0);
+    }
+
+    #[test]
+    fn test_start_session() {
+        let mut device = test_device();
+        device.next_cmdid();
+        device.rc_seqnum = 7;
+        device.stamp_instant = Instant::now() - Duration::from_secs(60);
+
+        device.start_session(&MiPacket::new(0x89ab_cdef, 5000));
+        assert_eq!(device.did(), 0x89ab_cdef);
+        assert_eq!(device.stamp(), 5000);
+        assert_eq!(device.next_cmdid(), FIRST_CMDID);
+        assert_eq!(device.rc_seqnum, 0);
+    }
+
+    #[test]
+    fn test_hello_deadline() {
+        // another host keeps sending packets, which must not extend the wait for the target device
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = socket.local_addr().unwrap();
+        let chatty = thread::spawn(move || {
+            let other = UdpSocket::bind("127.0.0.2:0").unwrap();
+            let start = Instant::now();
+            while start.elapsed() < HELLO_TIMEOUT * 2 {
+                other.send_to(b"chatter", addr).unwrap();
+                thread::sleep(Duration::from_millis(100));
+            }
+        });
+
+        let start = Instant::now();
+        assert!(matches!(hello(&socket, Ipv4Addr::LOCALHOST), Err(NoResponse)));
+        assert!(start.elapsed() < HELLO_TIMEOUT + Duration::from_millis(500));
+        chatty.join().unwrap();
+    }
+}
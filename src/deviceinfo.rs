@@ -1,9 +1,12 @@
 use crate::deviceinfo::Error::*;
 use crate::miiopayloads::*;
-use std::net::{UdpSocket, Ipv4Addr};
+use std::net::{UdpSocket, IpAddr, Ipv4Addr};
 use miiobin::{MI_DISCOVER_UDP_PORT, MiPacket};
 use std::error::Error as StdError;
-use std::{str, fmt, time::Duration};
+use std::{str, fmt, time::{Duration, Instant}};
+use std::io::ErrorKind;
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
 
 /// How many milliseconds to wait for a response to the discovery request
 const LISTEN_TIMEOUT: Duration = Duration::from_millis(5000);
@@ -13,7 +16,17 @@ pub enum Error {
     Socket(String),
     Packet(String),
     Parse(String),
+    /// Error reported by the device in its response: code and message
+    Device(i32, String),
+    /// The device replied with a result which was not expected for the command
+    Unexpected(String),
+    /// The command parameters were rejected before sending them to the device
+    InvalidArgument(String),
+    /// The device is not in a state in which the command can be safely sent
+    NotReady(String),
     NoResponse,
+    /// The device did not complete an operation in time
+    Timeout(String),
 }
 
 /// Return the device status
@@ -25,22 +38,88 @@ pub enum Error {
 /// `did` - target device ID
 /// `token` - encryption key
 /// `stamp` - the stamp to be used for the `get_status` method. One way to get the current stamp is to use the
-///         stamp value returned in a discovery response package.
+///         stamp value returned in a discovery response package. It is updated with the stamp of the response packet.
 /// `cmdid` - Command id. This value is used to match the content of a command (`get_status`) with the content of a
 ///         response (`StatusResponse`). Its value needs to be incremented for each command-response pair.
 ///
 pub fn status(socket: &UdpSocket, dip: Ipv4Addr, did: u32, token: &[u8; 16], stamp: &mut u32, cmdid: u32)
               -> Result<StatusResponse, Error>
 {
-    let mut comm_buf = [0u8;1024];
+    let result = send_command_as(socket, dip, did, token, stamp, cmdid, METHOD_GET_STATUS, json!([]))?;
+    Ok(StatusResponse { id: cmdid, result })
+}
+
+/// Send a miio command to a device, and return the `result` value of the matching response.
+///
+/// Responses whose `id` does not match `cmdid` (e.g. late responses to previous commands) are discarded, as well as
+/// packets which do not come from the device or cannot be decrypted. If no matching response arrives within
+/// `LISTEN_TIMEOUT`, then `Error::NoResponse` is returned. If the device replies with an `error` object, then an
+/// `Error::Device` is returned.
+///
+/// # Arguments
+///
+/// `socket` - UDP socket on which to transmit the command, and receive the response
+/// `dip` - target device IP
+/// `did` - target device ID
+/// `token` - encryption key
+/// `stamp` - the stamp to be used for the command packet. It is updated with the stamp of the response packet.
+/// `cmdid` - Command id, used to match the command with its response.
+/// `method` - name of the miio method, e.g. `get_status`, `miIO.info`, `app_start`
+/// `params` - method parameters, usually a JSON array or object
+///
+pub fn send_command(socket: &UdpSocket, dip: Ipv4Addr, did: u32, token: &[u8; 16], stamp: &mut u32, cmdid: u32,
+                    method: &str, params: Value) -> Result<Value, Error>
+{
+    let cmd = Command::new(cmdid, method, params);
+    let cmd_payload_str = match serde_json::to_string(&cmd) {
+        Ok(s) => s,
+        Err(e) => { return Err(Parse(e.to_string())); }
+    };
+
+    send_payload(socket, dip, did, token, *stamp, &cmd_payload_str)?;
 
-    // FIXME implement serialization for this
-    let status_cmd = StatusCommand::new(cmdid);
-    let cmd_payload_str = serde_json::to_string(&status_cmd).unwrap();
+    // late replies to previous commands do not extend the wait
+    let deadline = Instant::now() + LISTEN_TIMEOUT;
+    loop {
+        let payload_string = recv_payload(socket, dip, token, stamp, deadline)?;
+        let resp: Response = match serde_json::from_str(&payload_string[..find_last_closing_bracket(&payload_string)]) {
+            Ok(resp) => resp,
+            Err(e) => { return Err(Parse(e.to_string() + &payload_string)); }
+        };
 
-//    let cmd_payload_str = format!("{{\"method\": \"get_status\", \"id\": {}, \"params\": {{}}}}\0", cmdid);
+        if resp.id != cmdid {
+            continue;
+        }
+
+        return match resp.error {
+            Some(err) => Err(Device(err.code, err.message)),
+            None => Ok(resp.result)
+        };
+    }
+}
+
+/// Send a miio command to a device, and deserialize the `result` value of the matching response into `T`.
+///
+/// See `send_command` for a description of the arguments.
+///
+pub fn send_command_as<T: DeserializeOwned>(socket: &UdpSocket, dip: Ipv4Addr, did: u32, token: &[u8; 16],
+                                            stamp: &mut u32, cmdid: u32, method: &str, params: Value)
+                                            -> Result<T, Error>
+{
+    let result = send_command(socket, dip, did, token, stamp, cmdid, method, params)?;
+    match serde_json::from_value(result.clone()) {
+        Ok(typed) => Ok(typed),
+        Err(e) => Err(Parse(e.to_string() + " " + &result.to_string()))
+    }
+}
+
+/// Encrypt a command payload and send it to a device
+fn send_payload(socket: &UdpSocket, dip: Ipv4Addr, did: u32, token: &[u8; 16], stamp: u32,
+                cmd_payload_str: &str) -> Result<(), Error>
+{
+    let mut comm_buf = [0u8;1024];
 
-    let mut packet = MiPacket::new(did, *stamp);
+    let mut packet = MiPacket::new(did, stamp);
     packet.payload.extend_from_slice(cmd_payload_str.as_bytes());
     if let Err(e) = packet.encrypt(&token) { return Err(Packet(e.to_string())); }
     match packet.pack(&mut comm_buf, &token){
@@ -53,35 +132,64 @@ pub fn status(socket: &UdpSocket, dip: Ipv4Addr, did: u32, token: &[u8; 16], sta
         Err(e) => { return Err(Packet(e.to_string())); }
     }
 
-    if let Err(e) = socket.set_read_timeout(Option::Some(LISTEN_TIMEOUT)) { return Err(Socket(e.to_string())); }
-
-    return match socket.recv_from(&mut comm_buf)
-    {
-        Ok((amt, _src)) => {
-            match MiPacket::parse_decrypt(&comm_buf[..amt], &token) {
-                Ok(packet) => {
-                    if let Ok(payload_string) = String::from_utf8(packet.payload) {
-                        match serde_json::from_str(&payload_string[..find_last_closing_bracket(&payload_string)]) {
-                            Ok(info_resp) => {
-                                Ok(info_resp)
-                            }
-                            Err(e) => {
-                                Err(Parse(e.to_string() + &payload_string))
-                            }
-                        }
-                    } else { Err(Packet("Could not convert payload to UTF-8 string.".to_string())) }
+    Ok(())
+}
+
+/// Receive a packet from a device, and return its decrypted payload. `stamp` is updated with the packet stamp.
+///
+/// Packets which do not come from the device, or which cannot be decrypted with the token, are skipped. If no valid
+/// packet is received before `deadline`, then `Error::NoResponse` is returned.
+fn recv_payload(socket: &UdpSocket, dip: Ipv4Addr, token: &[u8; 16], stamp: &mut u32, deadline: Instant)
+                -> Result<String, Error>
+{
+    let mut comm_buf = [0u8;4096];
+
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining == Duration::from_secs(0) {
+            return Err(NoResponse);
+        }
+        if let Err(e) = socket.set_read_timeout(Option::Some(remaining)) { return Err(Socket(e.to_string())); }
+
+        match socket.recv_from(&mut comm_buf)
+        {
+            Ok((amt, src)) => {
+                if src.ip() != IpAddr::V4(dip) {
+                    continue;
+                }
+                if let Ok(packet) = MiPacket::parse_decrypt(&comm_buf[..amt], &token) {
+                    *stamp = packet.stamp;
+                    return match String::from_utf8(packet.payload) {
+                        Ok(payload_string) => Ok(payload_string),
+                        Err(_e) => Err(Packet("Could not convert payload to UTF-8 string.".to_string()))
+                    };
                 }
-                Err(e) => { Err(Packet(e.to_string())) }
+            }
+            Err(e) => {
+                return match e.kind() {
+                    ErrorKind::WouldBlock | ErrorKind::TimedOut => Err(NoResponse),
+                    _ => Err(Socket(e.to_string()))
+                };
             }
         }
-        Err(e) => { Err(Socket(e.to_string())) }
     }
 }
 
-/// FIXME add documentation
-#[allow(unused_variables)]
-pub fn info(socket: &UdpSocket, dip: Ipv4Addr, did: u32, token: &[u8; 16], cmdid: u32) {
-    println!("{{\"method\": \"get_status\", \"id\": {}, \"params\": {{}}}}", cmdid);
+/// Return the device information (model, firmware and hardware version, network configuration, etc.)
+///
+/// # Arguments
+///
+/// `socket` - UDP socket on which to transmit the `miIO.info` method, and receive the response
+/// `dip` - target device IP
+/// `did` - target device ID
+/// `token` - encryption key
+/// `stamp` - the stamp to be used for the `miIO.info` method. It is updated with the stamp of the response packet.
+/// `cmdid` - Command id, used to match the command with its response.
+///
+pub fn info(socket: &UdpSocket, dip: Ipv4Addr, did: u32, token: &[u8; 16], stamp: &mut u32, cmdid: u32)
+            -> Result<DeviceInfo, Error>
+{
+    send_command_as(socket, dip, did, token, stamp, cmdid, METHOD_INFO, json!([]))
 }
 
 impl StdError for Error {
@@ -90,7 +198,12 @@ impl StdError for Error {
             Error::Socket(_e) => "Socket error",
             Error::Packet(_e) => "Packet error",
             Error::Parse(_e) => "JSON parse error",
-            Error::NoResponse => "No response received"
+            Error::Device(_code, _msg) => "Device error",
+            Error::Unexpected(_e) => "Unexpected result",
+            Error::InvalidArgument(_e) => "Invalid argument",
+            Error::NotReady(_e) => "Device not ready",
+            Error::NoResponse => "No response received",
+            Error::Timeout(_e) => "Timed out"
         }
     }
 }
@@ -101,7 +214,40 @@ impl fmt::Display for Error {
             Error::Socket(e) => f.write_fmt(format_args!("Socket error: {}", e)),
             Error::Packet(e) => f.write_fmt(format_args!("Packet error: {}", e)),
             Error::Parse(e) => f.write_fmt(format_args!("Parse error for JSON payload: {}", e)),
+            Error::Device(code, msg) => f.write_fmt(format_args!("Device error {}: {}", code, msg)),
+            Error::Unexpected(e) => f.write_fmt(format_args!("Unexpected result: {}", e)),
+            Error::InvalidArgument(e) => f.write_fmt(format_args!("Invalid argument: {}", e)),
+            Error::NotReady(e) => f.write_fmt(format_args!("Device not ready: {}", e)),
             Error::NoResponse => f.write_fmt(format_args!("No response received")),
+            Error::Timeout(e) => f.write_fmt(format_args!("Timed out: {}", e)),
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_send_command_skips_bad_packets() {
+        // garbage datagrams from the device's address keep arriving, but the command still ends at the deadline
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = socket.local_addr().unwrap();
+        let sender = thread::spawn(move || {
+            let device = UdpSocket::bind("127.0.0.1:0").unwrap();
+            let start = Instant::now();
+            while start.elapsed() < LISTEN_TIMEOUT + Duration::from_secs(1) {
+                device.send_to(b"not a miio packet", addr).unwrap();
+                thread::sleep(Duration::from_millis(200));
+            }
+        });
+
+        let start = Instant::now();
+        let mut stamp = 0;
+        let result = send_command(&socket, Ipv4Addr::LOCALHOST, 1, &[0; 16], &mut stamp, 1, METHOD_INFO, json!([]));
+        assert!(matches!(result, Err(NoResponse)));
+        assert!(start.elapsed() < LISTEN_TIMEOUT + Duration::from_millis(500));
+        sender.join().unwrap();
+    }
+}
@@ -0,0 +1,130 @@
+//! The Do-Not-Disturb window of the vacuum robot, as reported by the `get_dnd_timer` method, and set with the
+//! `set_dnd_timer` method.
+//!
+//! During the window the robot does not resume cleaning, and does not play voice prompts. The window may span
+//! midnight, e.g. `22:00` to `08:00`.
+
+use serde::{Serialize, Deserialize};
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "DndTimerRepr", into = "DndTimerRepr")]
+pub struct DndWindow {
+    /// Start of the window, as `(hour, minute)`
+    pub start: (u32, u32),
+    /// End of the window, as `(hour, minute)`
+    pub end: (u32, u32),
+    pub enabled: bool,
+}
+
+/// The `get_dnd_timer` representation of the window
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct DndTimerRepr {
+    start_hour: u32,
+    start_minute: u32,
+    end_hour: u32,
+    end_minute: u32,
+    enabled: i32,
+}
+
+impl DndWindow {
+    /// Create an enabled window
+    pub fn new(start: (u32, u32), end: (u32, u32)) -> DndWindow {
+        DndWindow { start, end, enabled: true }
+    }
+
+    /// Check that the start and end of the window are valid times of day, and that the window is not empty
+    pub fn validate(&self) -> Result<(), String> {
+        for &(hour, minute) in [self.start, self.end].iter() {
+            if hour > 23 || minute > 59 {
+                return Err(format!("{:02}:{:02} is not a valid time of day", hour, minute));
+            }
+        }
+        if self.start == self.end {
+            return Err("the start and the end of the window are the same".to_string());
+        }
+        Ok(())
+    }
+
+    /// The window, as used in the parameters of the `set_dnd_timer` method
+    pub fn to_params(&self) -> [u32; 4] {
+        [self.start.0, self.start.1, self.end.0, self.end.1]
+    }
+}
+
+/// Parse a time of day, as `HH:MM`
+pub fn parse_time(s: &str) -> Result<(u32, u32), String> {
+    let mut parts = s.trim().splitn(2, ':');
+    let hour = parts.next().and_then(|h| h.parse::<u32>().ok());
+    let minute = parts.next().and_then(|m| m.parse::<u32>().ok());
+    match (hour, minute) {
+        (Some(hour), Some(minute)) if hour <= 23 && minute <= 59 => Ok((hour, minute)),
+        _ => Err(s.to_string())
+    }
+}
+
+impl From<DndTimerRepr> for DndWindow {
+    fn from(r: DndTimerRepr) -> DndWindow {
+        DndWindow {
+            start: (r.start_hour, r.start_minute),
+            end: (r.end_hour, r.end_minute),
+            enabled: r.enabled != 0
+        }
+    }
+}
+
+impl From<DndWindow> for DndTimerRepr {
+    fn from(w: DndWindow) -> DndTimerRepr {
+        DndTimerRepr {
+            start_hour: w.start.0,
+            start_minute: w.start.1,
+            end_hour: w.end.0,
+            end_minute: w.end.1,
+            enabled: w.enabled as i32
+        }
+    }
+}
+
+impl fmt::Display for DndWindow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_fmt(format_args!("{:02}:{:02} - {:02}:{:02} ({})", self.start.0, self.start.1, self.end.0, self.end.1,
+                                 if self.enabled { "enabled" } else { "disabled" }))
+    }
+}
+
+impl FromStr for DndWindow {
+    type Err = String;
+
+    /// Parse an enabled window from `HH:MM-HH:MM`
+    fn from_str(s: &str) -> Result<DndWindow, String> {
+        let mut parts = s.splitn(2, '-');
+        match (parts.next().map(parse_time), parts.next().map(parse_time)) {
+            (Some(Ok(start)), Some(Ok(end))) => Ok(DndWindow::new(start, end)),
+            _ => Err(s.to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dnd_window() {
+        let windows: Vec<DndWindow> = serde_json::from_str(
+            "[{\"start_hour\":22,\"start_minute\":0,\"end_hour\":8,\"end_minute\":30,\"enabled\":1}]").unwrap();
+        assert_eq!(windows, vec!(DndWindow::new((22, 0), (8, 30))));
+        assert_eq!(windows[0].to_params(), [22, 0, 8, 30]);
+        assert_eq!(windows[0].to_string(), "22:00 - 08:30 (enabled)");
+
+        assert_eq!(DndWindow::from_str("22:00-8:30"), Ok(DndWindow::new((22, 0), (8, 30))));
+        assert!(DndWindow::from_str("24:00-08:00").is_err());
+        assert!(DndWindow::from_str("22:60-08:00").is_err());
+        assert!(DndWindow::from_str("22:00").is_err());
+
+        assert!(DndWindow::new((22, 0), (8, 0)).validate().is_ok());
+        assert!(DndWindow::new((22, 0), (22, 0)).validate().is_err());
+        assert!(DndWindow::new((25, 0), (8, 0)).validate().is_err());
+    }
+}
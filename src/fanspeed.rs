@@ -0,0 +1,173 @@
+//! The suction power of the vacuum robot, as reported in the `fan_power` field of the `get_status` response.
+//!
+//! Older firmware (e.g. the original RoboRock S5 firmware) reports the suction power as a percentage, while newer
+//! firmware reports one of the codes `101` to `106`. The same numeric scheme is used by `set_custom_mode`, so a
+//! named preset has to be converted with the scheme of the target robot (see `FanSpeedScheme`).
+
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FanSpeed {
+    Quiet,
+    Balanced,
+    Turbo,
+    Max,
+    /// Suction power used when mopping (also called "gentle")
+    Mop,
+    /// Suction power which does not correspond to a named preset
+    Custom(i32),
+}
+
+/// The numeric scheme used by the firmware for the suction power
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FanSpeedScheme {
+    /// The suction power is a percentage (`38`, `60`, `75`, `100`), and `105` is used for mopping
+    Percentage,
+    /// The suction power is a percentage as used by the S5 firmware (`38`, `60`, `77`, `90`), and `105` is used for
+    /// mopping
+    PercentageS5,
+    /// The suction power is one of the codes `101` (quiet) to `105` (mop)
+    Code,
+}
+
+/// The named presets, in the order of the columns of `FanSpeedScheme::table`
+const PRESETS: [FanSpeed; 5] = [FanSpeed::Quiet, FanSpeed::Balanced, FanSpeed::Turbo, FanSpeed::Max, FanSpeed::Mop];
+
+impl FanSpeed {
+    /// The numeric suction power of the preset, for the given firmware scheme
+    pub fn fan_power(&self, scheme: FanSpeedScheme) -> i32 {
+        match *self {
+            FanSpeed::Custom(fan_power) => fan_power,
+            preset => {
+                let index = PRESETS.iter().position(|p| *p == preset).unwrap_or(0);
+                scheme.table()[index]
+            }
+        }
+    }
+
+    /// The preset of a numeric suction power, for the given firmware scheme
+    pub fn from_fan_power(fan_power: i32, scheme: FanSpeedScheme) -> FanSpeed {
+        match scheme.table().iter().position(|p| *p == fan_power) {
+            Some(index) => PRESETS[index],
+            None => FanSpeed::Custom(fan_power)
+        }
+    }
+}
+
+impl FanSpeedScheme {
+    /// All the known schemes
+    pub const ALL: [FanSpeedScheme; 3] = [FanSpeedScheme::Percentage, FanSpeedScheme::PercentageS5,
+                                          FanSpeedScheme::Code];
+
+    /// The suction power of each preset: quiet, balanced, turbo, max and mop
+    pub fn table(&self) -> [i32; 5] {
+        match *self {
+            FanSpeedScheme::Percentage => [38, 60, 75, 100, 105],
+            FanSpeedScheme::PercentageS5 => [38, 60, 77, 90, 105],
+            FanSpeedScheme::Code => [101, 102, 103, 104, 105],
+        }
+    }
+
+    /// Guess the firmware scheme from a suction power reported by the robot.
+    ///
+    /// `105` (mop) is used by all schemes, and is assumed to belong to the newer `Code` scheme. The suction powers
+    /// shared by both percentage schemes (quiet, balanced and custom percentages) are assumed to belong to
+    /// `Percentage`; use `from_model` to tell these apart.
+    pub fn detect(fan_power: i32) -> FanSpeedScheme {
+        if fan_power > 100 {
+            FanSpeedScheme::Code
+        } else if fan_power == 77 || fan_power == 90 {
+            FanSpeedScheme::PercentageS5
+        } else {
+            FanSpeedScheme::Percentage
+        }
+    }
+
+    /// Return the scheme of the models which are known to use one of the percentage schemes
+    ///
+    /// # Arguments
+    ///
+    /// `model` - the model, as reported by `miIO.info` (e.g. `roborock.vacuum.s5`)
+    ///
+    pub fn from_model(model: &str) -> Option<FanSpeedScheme> {
+        match model {
+            "rockrobo.vacuum.v1" => Some(FanSpeedScheme::Percentage),
+            "roborock.vacuum.s5" => Some(FanSpeedScheme::PercentageS5),
+            _ => None
+        }
+    }
+}
+
+impl From<i32> for FanSpeed {
+    /// The preset of a numeric suction power, for whichever scheme uses it
+    fn from(fan_power: i32) -> FanSpeed {
+        FanSpeed::from_fan_power(fan_power, FanSpeedScheme::detect(fan_power))
+    }
+}
+
+impl fmt::Display for FanSpeed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FanSpeed::Quiet => f.write_str("Quiet"),
+            FanSpeed::Balanced => f.write_str("Balanced"),
+            FanSpeed::Turbo => f.write_str("Turbo"),
+            FanSpeed::Max => f.write_str("Max"),
+            FanSpeed::Mop => f.write_str("Mop"),
+            FanSpeed::Custom(fan_power) => f.write_fmt(format_args!("Custom ({})", fan_power)),
+        }
+    }
+}
+
+impl FromStr for FanSpeed {
+    type Err = String;
+
+    /// Parse a preset name (`quiet`, `balanced`, `turbo`, `max`, `mop` or `gentle`), or a numeric suction power
+    fn from_str(s: &str) -> Result<FanSpeed, String> {
+        match s.to_lowercase().as_str() {
+            "quiet" => Ok(FanSpeed::Quiet),
+            "balanced" => Ok(FanSpeed::Balanced),
+            "turbo" => Ok(FanSpeed::Turbo),
+            "max" => Ok(FanSpeed::Max),
+            "mop" | "gentle" => Ok(FanSpeed::Mop),
+            other => match other.parse::<i32>() {
+                Ok(fan_power) => Ok(FanSpeed::Custom(fan_power)),
+                Err(_e) => Err(s.to_string())
+            }
+        }
+    }
+}
+
+impl FromStr for FanSpeedScheme {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<FanSpeedScheme, String> {
+        match s.to_lowercase().as_str() {
+            "percentage" | "percent" => Ok(FanSpeedScheme::Percentage),
+            "s5" => Ok(FanSpeedScheme::PercentageS5),
+            "code" => Ok(FanSpeedScheme::Code),
+            _ => Err(s.to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        for scheme in FanSpeedScheme::ALL.iter() {
+            for preset in PRESETS.iter() {
+                let fan_power = preset.fan_power(*scheme);
+                assert_eq!(FanSpeed::from_fan_power(fan_power, *scheme), *preset, "{:?} {:?}", scheme, preset);
+                assert_eq!(FanSpeed::from(fan_power), *preset, "{:?} {:?}", scheme, preset);
+            }
+        }
+        assert_eq!(FanSpeed::Max.fan_power(FanSpeedScheme::PercentageS5), 90);
+        assert_eq!(FanSpeed::from_fan_power(100, FanSpeedScheme::PercentageS5), FanSpeed::Custom(100));
+        assert_eq!(FanSpeedScheme::detect(77), FanSpeedScheme::PercentageS5);
+        assert_eq!(FanSpeedScheme::from_model("roborock.vacuum.s5"), Some(FanSpeedScheme::PercentageS5));
+        assert_eq!(FanSpeedScheme::from_model("roborock.vacuum.s6"), None);
+    }
+}
@@ -0,0 +1,184 @@
+//! A minimal HTTP server which serves a single file, so that the robot can download voice packs and firmware
+//! updates without internet access.
+//!
+//! The robot only needs plain `GET` requests, so only `GET` and `HEAD` are supported. Requests are handled one at a
+//! time, on a background thread which runs until the `FileServer` is dropped.
+
+use md5::{Md5, Digest};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4, TcpListener, TcpStream};
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// How often the server thread checks whether it should stop, while waiting for connections
+const ACCEPT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Timeout for reading a request, and for writing a response
+const CONNECTION_TIMEOUT: Duration = Duration::from_secs(30);
+
+pub struct FileServer {
+    addr: SocketAddr,
+    name: String,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl FileServer {
+    /// Start serving a file, as `http://<ip>:<port>/<file name>`
+    ///
+    /// # Arguments
+    ///
+    /// `path` - the file to serve
+    /// `ip` - the local IP on which to listen. It must be reachable by the robot.
+    /// `port` - the port on which to listen, or `0` to use any free port
+    ///
+    pub fn serve(path: &Path, ip: Ipv4Addr, port: u16) -> io::Result<FileServer> {
+        let name = match path.file_name().and_then(|n| n.to_str()) {
+            Some(name) => name.to_string(),
+            None => { return Err(io::Error::new(io::ErrorKind::InvalidInput, "invalid file name")); }
+        };
+        // fail early, rather than on the first request
+        File::open(path)?;
+
+        let listener = TcpListener::bind(SocketAddrV4::new(ip, port))?;
+        listener.set_nonblocking(true)?;
+        let addr = listener.local_addr()?;
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+        let thread_path = path.to_path_buf();
+        let thread_name = name.clone();
+        let thread = thread::spawn(move || {
+            while !thread_stop.load(Ordering::Relaxed) {
+                match listener.accept() {
+                    Ok((stream, _peer)) => {
+                        // errors only affect the current connection, and the robot will retry the download
+                        let _ = handle_connection(stream, &thread_path, &thread_name);
+                    }
+                    // no pending connection (`WouldBlock`), or a transient accept error
+                    Err(_e) => { thread::sleep(ACCEPT_POLL_INTERVAL); }
+                }
+            }
+        });
+
+        Ok(FileServer { addr, name, stop, thread: Some(thread) })
+    }
+
+    /// The address on which the server is listening
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// The URL of the served file
+    pub fn url(&self) -> String {
+        format!("http://{}/{}", self.addr, self.name)
+    }
+}
+
+impl Drop for FileServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Return the MD5 checksum of a file, as a lowercase hex string
+pub fn md5_hex(path: &Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Md5::new();
+    let mut buf = [0u8; 8192];
+    loop {
+        let len = file.read(&mut buf)?;
+        if len == 0 {
+            break;
+        }
+        hasher.update(&buf[..len]);
+    }
+    Ok(hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// Read a single request from a connection, and respond with the file or with an error status
+fn handle_connection(stream: TcpStream, path: &Path, name: &str) -> io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(CONNECTION_TIMEOUT))?;
+    stream.set_write_timeout(Some(CONNECTION_TIMEOUT))?;
+
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // skip the headers
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or("");
+    let target = parts.next().unwrap_or("");
+    let mut stream = stream;
+
+    if method != "GET" && method != "HEAD" {
+        return write_status(&mut stream, "405 Method Not Allowed");
+    }
+    // ignore any query string
+    if target.split('?').next() != Some(&format!("/{}", name)) {
+        return write_status(&mut stream, "404 Not Found");
+    }
+
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+    stream.write_all(format!("HTTP/1.1 200 OK\r\nContent-Type: application/octet-stream\r\nContent-Length: {}\r\n\
+                              Connection: close\r\n\r\n", len).as_bytes())?;
+    if method == "GET" {
+        io::copy(&mut file, &mut stream)?;
+    }
+    stream.flush()
+}
+
+/// Respond with an error status, and no body
+fn write_status(stream: &mut TcpStream, status: &str) -> io::Result<()> {
+    stream.write_all(format!("HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", status).as_bytes())?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use crate::testutil::TempPath;
+
+    fn http_get(server: &FileServer, target: &str) -> String {
+        let mut stream = TcpStream::connect(server.addr()).unwrap();
+        stream.write_all(format!("GET {} HTTP/1.1\r\nHost: {}\r\n\r\n", target, server.addr()).as_bytes()).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn test_file_server() {
+        let path = TempPath::new("voice.pkg");
+        fs::write(&path, "hello").unwrap();
+        assert_eq!(md5_hex(&path).unwrap(), "5d41402abc4b2a76b9719d911017c592");
+
+        let server = FileServer::serve(&path, Ipv4Addr::LOCALHOST, 0).unwrap();
+        let name = path.file_name().unwrap().to_str().unwrap();
+        assert_eq!(server.url(), format!("http://127.0.0.1:{}/{}", server.addr().port(), name));
+
+        let response = http_get(&server, &format!("/{}", name));
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("Content-Length: 5\r\n"));
+        assert!(response.ends_with("\r\n\r\nhello"));
+        assert!(http_get(&server, "/other.pkg").starts_with("HTTP/1.1 404 Not Found\r\n"));
+
+        drop(server);
+    }
+}
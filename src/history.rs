@@ -0,0 +1,338 @@
+//! Export of the cleaning history to files, for long term storage.
+//!
+//! The robot only keeps a limited number of clean records, so the history is exported incrementally: the records
+//! which are already present in the output file are not fetched from the robot again, and each record is written
+//! only once, identified by its start timestamp.
+//!
+//! Supported formats are CSV, JSON Lines, and (with the `sqlite` feature) an append-only SQLite database.
+//!
+
+use crate::deviceinfo;
+use crate::device::Device;
+use crate::miiopayloads::CleanRecord;
+use crate::history::Error::*;
+use std::collections::BTreeSet;
+use std::error::Error as StdError;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::str::FromStr;
+use std::fmt;
+
+/// The header line of CSV exports
+const CSV_HEADER: &str = "begin,end,duration,area,error,complete,start_type,clean_type,finish_reason";
+
+#[derive(Debug)]
+pub enum Error {
+    Command(deviceinfo::Error),
+    Io(String),
+    Parse(String),
+    #[cfg(feature = "sqlite")]
+    Sqlite(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    JsonLines,
+    #[cfg(feature = "sqlite")]
+    Sqlite,
+}
+
+/// Fetch the clean records which are not yet present in the output file from the device, and append them to the
+/// file. Returns the number of records which were appended.
+///
+/// # Arguments
+///
+/// `device` - session with the device
+/// `path` - output file. It is created if it does not exist.
+/// `format` - format of the output file
+///
+pub fn export(device: &mut Device, path: &Path, format: ExportFormat) -> Result<usize, Error> {
+    let mut known = existing_begins(path, format)?;
+
+    let summary = match device.clean_summary() {
+        Ok(summary) => summary,
+        Err(e) => { return Err(Command(e)); }
+    };
+
+    let mut records = Vec::new();
+    for id in summary.records {
+        // record ids are the start timestamps of the records, so known records need not be fetched again
+        if known.contains(&id) {
+            continue;
+        }
+        let record = match device.clean_record(id) {
+            Ok(record) => record,
+            Err(e) => { return Err(Command(e)); }
+        };
+        if known.insert(record.begin) {
+            records.push(record);
+        }
+    }
+
+    // the device lists the most recent records first, but the export is chronological
+    records.sort_by_key(|r| r.begin);
+    append_records(path, format, &records)?;
+
+    Ok(records.len())
+}
+
+/// Return the start timestamps of the records which are already present in an output file
+///
+/// # Arguments
+///
+/// `path` - output file. If it does not exist, then an empty set is returned.
+/// `format` - format of the output file
+///
+pub fn existing_begins(path: &Path, format: ExportFormat) -> Result<BTreeSet<u64>, Error> {
+    let mut begins = BTreeSet::new();
+    if !path.exists() {
+        return Ok(begins);
+    }
+
+    match format {
+        ExportFormat::Csv | ExportFormat::JsonLines => {
+            let file = match File::open(path) {
+                Ok(file) => file,
+                Err(e) => { return Err(Io(e.to_string())); }
+            };
+            for line in BufReader::new(file).lines() {
+                let line = match line {
+                    Ok(line) => line,
+                    Err(e) => { return Err(Io(e.to_string())); }
+                };
+                if line.trim().is_empty() || line == CSV_HEADER {
+                    continue;
+                }
+                let begin = if format == ExportFormat::Csv {
+                    match line.split(',').next().map(u64::from_str) {
+                        Some(Ok(begin)) => begin,
+                        _ => { return Err(Parse(line)); }
+                    }
+                } else {
+                    match serde_json::from_str::<CleanRecord>(&line) {
+                        Ok(record) => record.begin,
+                        Err(e) => { return Err(Parse(e.to_string() + " " + &line)); }
+                    }
+                };
+                begins.insert(begin);
+            }
+        }
+        #[cfg(feature = "sqlite")]
+        ExportFormat::Sqlite => {
+            let conn = sqlite_open(path)?;
+            let mut stmt = match conn.prepare("SELECT begin FROM clean_records") {
+                Ok(stmt) => stmt,
+                Err(e) => { return Err(Sqlite(e.to_string())); }
+            };
+            let rows = match stmt.query_map([], |row| row.get::<_, i64>(0)) {
+                Ok(rows) => rows,
+                Err(e) => { return Err(Sqlite(e.to_string())); }
+            };
+            for row in rows {
+                match row {
+                    Ok(begin) => { begins.insert(begin as u64); }
+                    Err(e) => { return Err(Sqlite(e.to_string())); }
+                }
+            }
+        }
+    }
+
+    Ok(begins)
+}
+
+/// Append clean records to an output file, creating it if it does not exist
+///
+/// # Arguments
+///
+/// `path` - output file
+/// `format` - format of the output file
+/// `records` - the records to append
+///
+pub fn append_records(path: &Path, format: ExportFormat, records: &[CleanRecord]) -> Result<(), Error> {
+    match format {
+        ExportFormat::Csv | ExportFormat::JsonLines => {
+            let mut file = match OpenOptions::new().create(true).append(true).open(path) {
+                Ok(file) => file,
+                Err(e) => { return Err(Io(e.to_string())); }
+            };
+            // an existing but empty file (e.g. created with `touch`) also needs the header
+            let is_empty = match file.metadata() {
+                Ok(metadata) => metadata.len() == 0,
+                Err(e) => { return Err(Io(e.to_string())); }
+            };
+            let mut out = String::new();
+            if is_empty && format == ExportFormat::Csv {
+                out.push_str(CSV_HEADER);
+                out.push('\n');
+            }
+            for r in records {
+                if format == ExportFormat::Csv {
+                    out.push_str(&format!("{},{},{},{},{},{},{},{},{}\n", r.begin, r.end, r.duration, r.area, r.error,
+                                          r.complete as u8, opt_to_string(r.start_type),
+                                          opt_to_string(r.clean_type), opt_to_string(r.finish_reason)));
+                } else {
+                    out.push_str(&serde_json::to_string(r).unwrap());
+                    out.push('\n');
+                }
+            }
+            if let Err(e) = file.write_all(out.as_bytes()) { return Err(Io(e.to_string())); }
+        }
+        #[cfg(feature = "sqlite")]
+        ExportFormat::Sqlite => {
+            let mut conn = sqlite_open(path)?;
+            let tx = match conn.transaction() {
+                Ok(tx) => tx,
+                Err(e) => { return Err(Sqlite(e.to_string())); }
+            };
+            for r in records {
+                if let Err(e) = tx.execute(
+                    "INSERT OR IGNORE INTO clean_records (begin, end, duration, area, error, complete, start_type, \
+                     clean_type, finish_reason) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                    rusqlite::params![r.begin as i64, r.end as i64, r.duration as i64, r.area as i64, r.error,
+                                      r.complete, r.start_type, r.clean_type, r.finish_reason]) {
+                    return Err(Sqlite(e.to_string()));
+                }
+            }
+            if let Err(e) = tx.commit() { return Err(Sqlite(e.to_string())); }
+        }
+    }
+
+    Ok(())
+}
+
+/// Open an SQLite export database, and create the records table if it does not exist
+#[cfg(feature = "sqlite")]
+fn sqlite_open(path: &Path) -> Result<rusqlite::Connection, Error> {
+    let conn = match rusqlite::Connection::open(path) {
+        Ok(conn) => conn,
+        Err(e) => { return Err(Sqlite(e.to_string())); }
+    };
+    if let Err(e) = conn.execute(
+        "CREATE TABLE IF NOT EXISTS clean_records (begin INTEGER PRIMARY KEY, end INTEGER NOT NULL, \
+         duration INTEGER NOT NULL, area INTEGER NOT NULL, error INTEGER NOT NULL, complete INTEGER NOT NULL, \
+         start_type INTEGER, clean_type INTEGER, finish_reason INTEGER)", []) {
+        return Err(Sqlite(e.to_string()));
+    }
+    Ok(conn)
+}
+
+/// Format an optional value for CSV output, where a missing value is an empty field
+fn opt_to_string(opt: Option<i32>) -> String {
+    match opt {
+        Some(v) => v.to_string(),
+        None => String::new()
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    /// Parse an export format name: `csv`, `jsonl` or `sqlite`
+    fn from_str(s: &str) -> Result<ExportFormat, String> {
+        match s.to_lowercase().as_str() {
+            "csv" => Ok(ExportFormat::Csv),
+            "jsonl" => Ok(ExportFormat::JsonLines),
+            #[cfg(feature = "sqlite")]
+            "sqlite" => Ok(ExportFormat::Sqlite),
+            _ => Err(s.to_string())
+        }
+    }
+}
+
+impl StdError for Error {
+    fn description(&self) -> &str {
+        match &*self {
+            Error::Command(_e) => "Command error",
+            Error::Io(_e) => "I/O error",
+            Error::Parse(_e) => "Parse error",
+            #[cfg(feature = "sqlite")]
+            Error::Sqlite(_e) => "SQLite error",
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &*self {
+            Error::Command(e) => f.write_fmt(format_args!("{}", e)),
+            Error::Io(e) => f.write_fmt(format_args!("I/O error: {}", e)),
+            Error::Parse(e) => f.write_fmt(format_args!("Could not parse exported record: {}", e)),
+            #[cfg(feature = "sqlite")]
+            Error::Sqlite(e) => f.write_fmt(format_args!("SQLite error: {}", e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::TempPath;
+    use std::fs;
+
+    fn test_records() -> Vec<CleanRecord> {
+        vec!(
+            CleanRecord { begin: 1620033312, end: 1620035418, duration: 1942, area: 31812500, error: 0,
+                          complete: true, start_type: Some(2), ..Default::default() },
+            CleanRecord { begin: 1620119712, end: 1620120012, duration: 280, area: 2500000, error: 8,
+                          complete: false, ..Default::default() },
+        )
+    }
+
+    #[test]
+    fn test_csv_export() {
+        let path = TempPath::new("export.csv");
+        let records = test_records();
+        append_records(&path, ExportFormat::Csv, &records[..1]).unwrap();
+        append_records(&path, ExportFormat::Csv, &records[1..]).unwrap();
+
+        let content = fs::read_to_string(&path).unwrap();
+        assert_eq!(content, format!("{}\n1620033312,1620035418,1942,31812500,0,1,2,,\n\
+                                     1620119712,1620120012,280,2500000,8,0,,,\n", CSV_HEADER));
+        let begins = existing_begins(&path, ExportFormat::Csv).unwrap();
+        assert_eq!(begins.into_iter().collect::<Vec<u64>>(), vec!(1620033312, 1620119712));
+    }
+
+    #[test]
+    fn test_csv_export_empty_file() {
+        let path = TempPath::new("empty.csv");
+        fs::write(&path, "").unwrap();
+        append_records(&path, ExportFormat::Csv, &test_records()[..1]).unwrap();
+
+        let content = fs::read_to_string(&path).unwrap();
+        assert_eq!(content, format!("{}\n1620033312,1620035418,1942,31812500,0,1,2,,\n", CSV_HEADER));
+    }
+
+    #[test]
+    fn test_jsonl_export() {
+        let path = TempPath::new("export.jsonl");
+        let records = test_records();
+        append_records(&path, ExportFormat::JsonLines, &records).unwrap();
+
+        let content = fs::read_to_string(&path).unwrap();
+        let read: Vec<CleanRecord> = content.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        assert_eq!(read, records);
+        let begins = existing_begins(&path, ExportFormat::JsonLines).unwrap();
+        assert_eq!(begins.len(), 2);
+    }
+
+    #[test]
+    fn test_missing_export() {
+        let path = TempPath::new("missing.csv");
+        assert!(existing_begins(&path, ExportFormat::Csv).unwrap().is_empty());
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn test_sqlite_export() {
+        let path = TempPath::new("export.sqlite");
+        let records = test_records();
+        append_records(&path, ExportFormat::Sqlite, &records).unwrap();
+        // appending the same records again must not duplicate them
+        append_records(&path, ExportFormat::Sqlite, &records).unwrap();
+
+        let begins = existing_begins(&path, ExportFormat::Sqlite).unwrap();
+        assert_eq!(begins.into_iter().collect::<Vec<u64>>(), vec!(1620033312, 1620119712));
+    }
+}
@@ -1,4 +1,24 @@
 pub mod discovery;
 pub mod provisioning;
 pub mod deviceinfo;
+pub mod device;
 pub mod miiopayloads;
+pub mod vacuumstate;
+pub mod vacuumerror;
+pub mod fanspeed;
+pub mod consumable;
+pub mod history;
+pub mod zone;
+pub mod room;
+pub mod dnd;
+pub mod timer;
+pub mod fileserver;
+pub mod sound;
+pub mod ota;
+pub mod map;
+pub mod render;
+pub mod restriction;
+pub mod carpet;
+
+#[cfg(test)]
+mod testutil;
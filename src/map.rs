@@ -0,0 +1,462 @@
+//! Parser for the RRMap format, in which the robot uploads its map (see `Device::map_url`).
+//!
+//! The map is a gzipped blob, which starts with a header:
+//!
+//! | offset | size | field                          |
+//! |--------|------|--------------------------------|
+//! | `0x00` | 2    | magic, `rr`                    |
+//! | `0x02` | 2    | header length                  |
+//! | `0x04` | 4    | length of the blocks           |
+//! | `0x08` | 2    | major version                  |
+//! | `0x0A` | 2    | minor version                  |
+//! | `0x0C` | 4    | map index                      |
+//! | `0x10` | 4    | map sequence                   |
+//!
+//! followed by a sequence of blocks, and a SHA-1 digest of the header and the blocks. Each block starts with its
+//! type (2 bytes), the length of its header (2 bytes) and the length of its data (4 bytes). All values are little
+//! endian, and all coordinates are in millimeters, in the same coordinate space as zones (see `zone`), except for
+//! the image, which has one pixel for each `PIXEL_SIZE` millimeters.
+
+use crate::map::Error::*;
+use crate::zone::Zone;
+use flate2::read::GzDecoder;
+use std::collections::BTreeSet;
+use std::error::Error as StdError;
+use std::fmt;
+use std::io::Read;
+
+/// The size of a map image pixel, in millimeters
+pub const PIXEL_SIZE: u32 = 50;
+
+const MAGIC: &[u8; 2] = b"rr";
+const HEADER_LEN: usize = 0x14;
+const BLOCK_HEADER_LEN: usize = 8;
+
+const BLOCK_CHARGER: u16 = 1;
+const BLOCK_IMAGE: u16 = 2;
+const BLOCK_PATH: u16 = 3;
+const BLOCK_GOTO_PATH: u16 = 4;
+const BLOCK_GOTO_PREDICTED_PATH: u16 = 5;
+const BLOCK_CURRENTLY_CLEANED_ZONES: u16 = 6;
+const BLOCK_GOTO_TARGET: u16 = 7;
+const BLOCK_ROBOT_POSITION: u16 = 8;
+const BLOCK_NO_GO_AREAS: u16 = 9;
+const BLOCK_VIRTUAL_WALLS: u16 = 10;
+const BLOCK_CURRENTLY_CLEANED_SEGMENTS: u16 = 11;
+const BLOCK_NO_MOP_AREAS: u16 = 12;
+
+#[derive(Debug)]
+pub enum Error {
+    /// The map could not be decompressed
+    Decompress(String),
+    /// The map is truncated, or is not in the RRMap format
+    Format(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Point {
+    pub x: u32,
+    pub y: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MapHeader {
+    pub major_version: u16,
+    pub minor_version: u16,
+    pub map_index: u32,
+    pub map_sequence: u32,
+}
+
+/// The kind of area covered by a pixel of the map image
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pixel {
+    /// Not explored
+    Outside,
+    Wall,
+    /// Floor which does not belong to a segment
+    Floor,
+    /// Area seen by the laser distance sensor, but not visited
+    Scanned,
+    Obstacle,
+    /// Floor which belongs to the segment (room) with the given id
+    Segment(u8),
+    /// A raw pixel value whose type (the lower 3 bits) is not known to this library
+    Unknown(u8),
+}
+
+/// The occupancy grid of the map. Row `0` is the lowest `y` coordinate.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct MapImage {
+    /// Offset of the first row, in pixels
+    pub top: u32,
+    /// Offset of the first column, in pixels
+    pub left: u32,
+    pub width: u32,
+    pub height: u32,
+    /// Number of segments, as reported by the robot (`0` on firmware without segments)
+    pub segment_count: u32,
+    /// Raw pixel values, row by row
+    pub pixels: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct MapPath {
+    /// Heading of the robot at the end of the path, in degrees
+    pub angle: u32,
+    pub points: Vec<Point>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VirtualWall {
+    pub start: Point,
+    pub end: Point,
+}
+
+/// A quadrilateral area, given by its four corners
+pub type Area = [Point; 4];
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct RRMap {
+    pub header: MapHeader,
+    pub charger: Option<Point>,
+    pub image: Option<MapImage>,
+    pub robot_position: Option<Point>,
+    /// Heading of the robot, in degrees
+    pub robot_angle: Option<i32>,
+    /// The path driven by the robot during the current (or last) cleaning
+    pub path: Option<MapPath>,
+    /// The path driven by the robot while going to a target
+    pub goto_path: Option<MapPath>,
+    /// The path which the robot plans to drive to a target
+    pub goto_predicted_path: Option<MapPath>,
+    pub goto_target: Option<Point>,
+    /// Zones of the current zoned cleaning
+    pub cleaned_zones: Vec<Zone>,
+    /// Segments of the current segment cleaning
+    pub cleaned_segments: Vec<u8>,
+    pub no_go_areas: Vec<Area>,
+    pub no_mop_areas: Vec<Area>,
+    pub virtual_walls: Vec<VirtualWall>,
+}
+
+impl Pixel {
+    /// Classify a raw pixel value
+    pub fn from_raw(value: u8) -> Pixel {
+        match value {
+            0x00 => Pixel::Outside,
+            0x01 => Pixel::Wall,
+            0xFF => Pixel::Floor,
+            0x07 => Pixel::Scanned,
+            // the lower 3 bits are the pixel type, and the upper 5 bits are the segment id
+            v => match v & 0x07 {
+                0x00 => Pixel::Obstacle,
+                0x01 => Pixel::Wall,
+                0x07 => Pixel::Segment(v >> 3),
+                _ => Pixel::Unknown(v),
+            }
+        }
+    }
+}
+
+impl MapImage {
+    /// Return the pixel at a column and row of the image, or `None` if it is outside of the image
+    pub fn pixel(&self, column: u32, row: u32) -> Option<Pixel> {
+        if column >= self.width || row >= self.height {
+            return None;
+        }
+        self.pixels.get(row as usize * self.width as usize + column as usize).map(|v| Pixel::from_raw(*v))
+    }
+
+    /// Return the robot coordinates of the lower left corner of a pixel, or `None` if they are out of range
+    pub fn to_robot(&self, column: u32, row: u32) -> Option<Point> {
+        let x = (self.left as u64 + column as u64) * PIXEL_SIZE as u64;
+        let y = (self.top as u64 + row as u64) * PIXEL_SIZE as u64;
+        if x > u32::MAX as u64 || y > u32::MAX as u64 {
+            return None;
+        }
+        Some(Point { x: x as u32, y: y as u32 })
+    }
+
+    /// Return the ids of the segments which appear in the image
+    pub fn segments(&self) -> BTreeSet<u8> {
+        self.pixels.iter().filter_map(|v| match Pixel::from_raw(*v) {
+            Pixel::Segment(id) => Some(id),
+            _ => None
+        }).collect()
+    }
+}
+
+/// Decompress and parse a map
+///
+/// # Arguments
+///
+/// `data` - the gzipped map. A map which is not gzipped is also accepted.
+///
+pub fn parse(data: &[u8]) -> Result<RRMap, Error> {
+    if data.starts_with(&[0x1f, 0x8b]) {
+        let mut raw = Vec::new();
+        if let Err(e) = GzDecoder::new(data).read_to_end(&mut raw) {
+            return Err(Decompress(e.to_string()));
+        }
+        parse_raw(&raw)
+    } else {
+        parse_raw(data)
+    }
+}
+
+/// Parse a decompressed map
+pub fn parse_raw(data: &[u8]) -> Result<RRMap, Error> {
+    if data.len() < HEADER_LEN || &data[..2] != MAGIC {
+        return Err(Format("missing RRMap header".to_string()));
+    }
+    let header_len = u16_at(data, 0x02)? as usize;
+    let blocks_len = u32_at(data, 0x04)? as usize;
+    let end = header_len + blocks_len;
+    if header_len < HEADER_LEN || end > data.len() {
+        return Err(Format(format!("invalid map length {}, the map has {} bytes", end, data.len())));
+    }
+
+    let header = MapHeader {
+        major_version: u16_at(data, 0x08)?,
+        minor_version: u16_at(data, 0x0A)?,
+        map_index: u32_at(data, 0x0C)?,
+        map_sequence: u32_at(data, 0x10)?,
+    };
+    let mut map = RRMap { header, ..Default::default() };
+
+    let mut offset = header_len;
+    while offset < end {
+        let block_type = u16_at(data, offset)?;
+        let block_header_len = u16_at(data, offset + 2)? as usize;
+        let block_data_len = u32_at(data, offset + 4)? as usize;
+        let block_end = offset + block_header_len + block_data_len;
+        if block_header_len < BLOCK_HEADER_LEN || block_end > end {
+            return Err(Format(format!("block {} at offset {} is truncated", block_type, offset)));
+        }
+        let block = &data[offset..block_end];
+        parse_block(&mut map, block_type, block, block_header_len)?;
+        offset = block_end;
+    }
+
+    Ok(map)
+}
+
+/// Parse a single block into the map. Unknown blocks are ignored.
+///
+/// # Arguments
+///
+/// `block` - the block, including its header
+/// `header_len` - the length of the block header
+///
+fn parse_block(map: &mut RRMap, block_type: u16, block: &[u8], header_len: usize) -> Result<(), Error> {
+    match block_type {
+        BLOCK_CHARGER => {
+            map.charger = Some(point_i32_at(block, 0x08)?);
+        }
+        BLOCK_IMAGE => {
+            // newer firmware adds the number of segments to the block header
+            let (segment_count, params) = if header_len > 24 { (u32_at(block, 0x08)?, 0x0C) } else { (0, 0x08) };
+            let top = u32_at(block, params)?;
+            let left = u32_at(block, params + 0x04)?;
+            let height = u32_at(block, params + 0x08)?;
+            let width = u32_at(block, params + 0x0C)?;
+            let pixels = &block[header_len..];
+            if (pixels.len() as u64) < width as u64 * height as u64 {
+                return Err(Format(format!("image of {}x{} pixels has only {} bytes", width, height, pixels.len())));
+            }
+            let image_end = MapImage { top, left, width, height, segment_count, pixels: Vec::new() }
+                .to_robot(width, height);
+            if image_end.is_none() {
+                return Err(Format(format!("image at ({}, {}) is outside of the robot coordinates", left, top)));
+            }
+            map.image = Some(MapImage { top, left, width, height, segment_count, pixels: pixels.to_vec() });
+        }
+        BLOCK_PATH | BLOCK_GOTO_PATH | BLOCK_GOTO_PREDICTED_PATH => {
+            let angle = u32_at(block, 0x10)?;
+            let points = points_at(block, header_len, (block.len() - header_len) / 4)?;
+            let path = Some(MapPath { angle, points });
+            match block_type {
+                BLOCK_PATH => { map.path = path; }
+                BLOCK_GOTO_PATH => { map.goto_path = path; }
+                _ => { map.goto_predicted_path = path; }
+            }
+        }
+        BLOCK_CURRENTLY_CLEANED_ZONES => {
+            let count = u32_at(block, 0x08)? as usize;
+            let points = points_at(block, header_len, count * 2)?;
+            map.cleaned_zones = points.chunks(2)
+                .map(|c| Zone::new(c[0].x, c[0].y, c[1].x, c[1].y, 1))
+                .collect();
+        }
+        BLOCK_GOTO_TARGET => {
+            map.goto_target = Some(points_at(block, header_len, 1)?[0]);
+        }
+        BLOCK_ROBOT_POSITION => {
+            map.robot_position = Some(point_i32_at(block, header_len)?);
+            // older firmware does not report the heading
+            if block.len() >= header_len + 12 {
+                map.robot_angle = Some(u32_at(block, header_len + 8)? as i32);
+            }
+        }
+        BLOCK_NO_GO_AREAS | BLOCK_NO_MOP_AREAS => {
+            let count = u32_at(block, 0x08)? as usize;
+            let points = points_at(block, header_len, count * 4)?;
+            let areas = points.chunks(4).map(|c| [c[0], c[1], c[2], c[3]]).collect();
+            if block_type == BLOCK_NO_GO_AREAS {
+                map.no_go_areas = areas;
+            } else {
+                map.no_mop_areas = areas;
+            }
+        }
+        BLOCK_VIRTUAL_WALLS => {
+            let count = u32_at(block, 0x08)? as usize;
+            let points = points_at(block, header_len, count * 2)?;
+            map.virtual_walls = points.chunks(2).map(|c| VirtualWall { start: c[0], end: c[1] }).collect();
+        }
+        BLOCK_CURRENTLY_CLEANED_SEGMENTS => {
+            let count = u32_at(block, 0x08)? as usize;
+            match block.get(header_len..header_len + count) {
+                Some(segments) => { map.cleaned_segments = segments.to_vec(); }
+                None => { return Err(Format(format!("block {} is truncated", block_type))); }
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn u16_at(buf: &[u8], offset: usize) -> Result<u16, Error> {
+    match buf.get(offset..offset + 2) {
+        Some(b) => Ok(u16::from_le_bytes([b[0], b[1]])),
+        None => Err(Format(format!("unexpected end of data at offset {}", offset)))
+    }
+}
+
+fn u32_at(buf: &[u8], offset: usize) -> Result<u32, Error> {
+    match buf.get(offset..offset + 4) {
+        Some(b) => Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]])),
+        None => Err(Format(format!("unexpected end of data at offset {}", offset)))
+    }
+}
+
+/// Read a point with 32 bit coordinates. Negative coordinates are clamped to `0`.
+fn point_i32_at(buf: &[u8], offset: usize) -> Result<Point, Error> {
+    let x = u32_at(buf, offset)? as i32;
+    let y = u32_at(buf, offset + 4)? as i32;
+    Ok(Point { x: x.max(0) as u32, y: y.max(0) as u32 })
+}
+
+/// Read `count` points with 16 bit coordinates
+fn points_at(buf: &[u8], offset: usize, count: usize) -> Result<Vec<Point>, Error> {
+    (0..count).map(|i| {
+        let x = u16_at(buf, offset + i * 4)?;
+        let y = u16_at(buf, offset + i * 4 + 2)?;
+        Ok(Point { x: x as u32, y: y as u32 })
+    }).collect()
+}
+
+impl StdError for Error {
+    fn description(&self) -> &str {
+        match &*self {
+            Error::Decompress(_e) => "Decompression error",
+            Error::Format(_e) => "Map format error",
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &*self {
+            Error::Decompress(e) => f.write_fmt(format_args!("Could not decompress map: {}", e)),
+            Error::Format(e) => f.write_fmt(format_args!("Invalid map: {}", e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURE: &[u8] = include_bytes!("../tests/fixtures/map.rrmap.gz");
+
+    fn p(x: u32, y: u32) -> Point {
+        Point { x, y }
+    }
+
+    #[test]
+    fn test_parse_fixture() {
+        let map = parse(FIXTURE).unwrap();
+        assert_eq!(map.header, MapHeader { major_version: 1, minor_version: 0, map_index: 1, map_sequence: 42 });
+        assert_eq!(map.charger, Some(p(25500, 25500)));
+        assert_eq!(map.robot_position, Some(p(25700, 25650)));
+        assert_eq!(map.robot_angle, Some(45));
+        assert_eq!(map.goto_target, Some(p(26200, 25900)));
+
+        let path = map.path.unwrap();
+        assert_eq!(path.angle, 90);
+        assert_eq!(path.points, vec!(p(25500, 25500), p(25600, 25550), p(25700, 25650)));
+        assert_eq!(map.goto_path.unwrap().points, vec!(p(25700, 25650), p(26000, 25800)));
+        assert_eq!(map.goto_predicted_path.unwrap().points, vec!(p(26000, 25800), p(26200, 25900)));
+
+        assert_eq!(map.cleaned_zones, vec!(Zone::new(24800, 25000, 25900, 25300, 1)));
+        assert_eq!(map.cleaned_segments, vec!(16, 17));
+        assert_eq!(map.no_go_areas, vec!([p(24600, 24900), p(25100, 24900), p(25100, 25200), p(24600, 25200)]));
+        assert_eq!(map.no_mop_areas, vec!([p(25200, 25100), p(25500, 25100), p(25500, 25300), p(25200, 25300)]));
+        assert_eq!(map.virtual_walls, vec!(VirtualWall { start: p(24500, 25000), end: p(24500, 25400) },
+                                           VirtualWall { start: p(25200, 24800), end: p(25600, 24800) }));
+    }
+
+    #[test]
+    fn test_parse_image() {
+        let map = parse(FIXTURE).unwrap();
+        let image = map.image.unwrap();
+        assert_eq!((image.top, image.left, image.width, image.height), (500, 490, 8, 6));
+        assert_eq!(image.segment_count, 2);
+        assert_eq!(image.pixel(0, 0), Some(Pixel::Outside));
+        assert_eq!(image.pixel(1, 0), Some(Pixel::Wall));
+        assert_eq!(image.pixel(1, 1), Some(Pixel::Segment(16)));
+        assert_eq!(image.pixel(6, 1), Some(Pixel::Segment(17)));
+        assert_eq!(image.pixel(5, 2), Some(Pixel::Floor));
+        assert_eq!(image.pixel(2, 3), Some(Pixel::Obstacle));
+        assert_eq!(image.pixel(6, 4), Some(Pixel::Scanned));
+        assert_eq!(image.pixel(8, 0), None);
+        assert_eq!(image.to_robot(1, 2), Some(p(24550, 25100)));
+        assert_eq!(MapImage { left: u32::MAX / PIXEL_SIZE, pixels: Vec::new(), ..image }.to_robot(1, 0), None);
+        assert_eq!(image.segments().into_iter().collect::<Vec<u8>>(), vec!(16, 17));
+    }
+
+    #[test]
+    fn test_pixel_from_raw() {
+        assert_eq!(Pixel::from_raw(0x00), Pixel::Outside);
+        assert_eq!(Pixel::from_raw(0x01), Pixel::Wall);
+        assert_eq!(Pixel::from_raw(0x07), Pixel::Scanned);
+        assert_eq!(Pixel::from_raw(0xFF), Pixel::Floor);
+        assert_eq!(Pixel::from_raw(0x08), Pixel::Obstacle);
+        assert_eq!(Pixel::from_raw(0x09), Pixel::Wall);
+        assert_eq!(Pixel::from_raw(0x0F), Pixel::Segment(1));
+        assert_eq!(Pixel::from_raw(0xF7), Pixel::Segment(30));
+        assert_eq!(Pixel::from_raw(0x02), Pixel::Unknown(0x02));
+        assert_eq!(Pixel::from_raw(0x86), Pixel::Unknown(0x86));
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert!(parse(b"").is_err());
+        assert!(parse(b"not a map at all, but long enough").is_err());
+        assert!(parse(&FIXTURE[..100]).is_err());
+
+        let mut raw = Vec::new();
+        GzDecoder::new(FIXTURE).read_to_end(&mut raw).unwrap();
+        assert!(parse(&raw).is_ok());
+        assert!(parse(&raw[..200]).is_err());
+
+        // move the image far outside of the robot coordinates
+        let mut offset = u16_at(&raw, 0x02).unwrap() as usize;
+        while u16_at(&raw, offset).unwrap() != BLOCK_IMAGE {
+            offset += u16_at(&raw, offset + 2).unwrap() as usize + u32_at(&raw, offset + 4).unwrap() as usize;
+        }
+        let params = if u16_at(&raw, offset + 2).unwrap() > 24 { 0x0C } else { 0x08 };
+        raw[offset + params + 0x04..offset + params + 0x08].copy_from_slice(&(u32::MAX - 4).to_le_bytes());
+        assert!(parse(&raw).is_err());
+    }
+}
@@ -1,11 +1,116 @@
 use serde::{Serialize, Deserialize};
+use serde_json::Value;
 use std::str::{FromStr};
+use crate::vacuumstate::VacuumState;
+use crate::vacuumerror::VacuumError;
+use crate::fanspeed::FanSpeed;
+use crate::consumable::Consumable;
+use crate::sound::SoundInstallState;
+use std::time::Duration;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
 
-const METHOD_GET_STATUS_VAL:  &'static str = "get_status";
+pub const METHOD_GET_STATUS: &'static str = "get_status";
+pub const METHOD_INFO: &'static str = "miIO.info";
+pub const METHOD_CONFIG_ROUTER: &'static str = "miIO.config_router";
+pub const METHOD_APP_START: &'static str = "app_start";
+pub const METHOD_APP_PAUSE: &'static str = "app_pause";
+pub const METHOD_APP_STOP: &'static str = "app_stop";
+pub const METHOD_APP_CHARGE: &'static str = "app_charge";
+pub const METHOD_APP_SPOT: &'static str = "app_spot";
+pub const METHOD_FIND_ME: &'static str = "find_me";
+pub const METHOD_GET_CUSTOM_MODE: &'static str = "get_custom_mode";
+pub const METHOD_SET_CUSTOM_MODE: &'static str = "set_custom_mode";
+pub const METHOD_GET_CONSUMABLE: &'static str = "get_consumable";
+pub const METHOD_RESET_CONSUMABLE: &'static str = "reset_consumable";
+pub const METHOD_GET_CLEAN_SUMMARY: &'static str = "get_clean_summary";
+pub const METHOD_GET_CLEAN_RECORD: &'static str = "get_clean_record";
+pub const METHOD_APP_ZONED_CLEAN: &'static str = "app_zoned_clean";
+pub const METHOD_APP_GOTO_TARGET: &'static str = "app_goto_target";
+pub const METHOD_APP_RC_START: &'static str = "app_rc_start";
+pub const METHOD_APP_RC_MOVE: &'static str = "app_rc_move";
+pub const METHOD_APP_RC_END: &'static str = "app_rc_end";
+pub const METHOD_GET_ROOM_MAPPING: &'static str = "get_room_mapping";
+pub const METHOD_APP_SEGMENT_CLEAN: &'static str = "app_segment_clean";
+pub const METHOD_GET_DND_TIMER: &'static str = "get_dnd_timer";
+pub const METHOD_SET_DND_TIMER: &'static str = "set_dnd_timer";
+pub const METHOD_CLOSE_DND_TIMER: &'static str = "close_dnd_timer";
+pub const METHOD_GET_TIMER: &'static str = "get_timer";
+pub const METHOD_SET_TIMER: &'static str = "set_timer";
+pub const METHOD_UPD_TIMER: &'static str = "upd_timer";
+pub const METHOD_DEL_TIMER: &'static str = "del_timer";
+pub const METHOD_GET_SOUND_VOLUME: &'static str = "get_sound_volume";
+pub const METHOD_CHANGE_SOUND_VOLUME: &'static str = "change_sound_volume";
+pub const METHOD_TEST_SOUND_VOLUME: &'static str = "test_sound_volume";
+pub const METHOD_GET_CURRENT_SOUND: &'static str = "get_current_sound";
+pub const METHOD_DNLD_INSTALL_SOUND: &'static str = "dnld_install_sound";
+pub const METHOD_GET_SOUND_PROGRESS: &'static str = "get_sound_progress";
+pub const METHOD_OTA: &'static str = "miIO.ota";
+pub const METHOD_GET_OTA_STATE: &'static str = "miIO.get_ota_state";
+pub const METHOD_GET_OTA_PROGRESS: &'static str = "miIO.get_ota_progress";
+pub const METHOD_GET_MAP_V1: &'static str = "get_map_v1";
+pub const METHOD_SAVE_MAP: &'static str = "save_map";
+pub const METHOD_SET_LAB_STATUS: &'static str = "set_lab_status";
+pub const METHOD_GET_MULTI_MAPS_LIST: &'static str = "get_multi_maps_list";
+pub const METHOD_LOAD_MULTI_MAP: &'static str = "load_multi_map";
+pub const METHOD_NAME_SEGMENT: &'static str = "name_segment";
+pub const METHOD_GET_CARPET_MODE: &'static str = "get_carpet_mode";
+pub const METHOD_SET_CARPET_MODE: &'static str = "set_carpet_mode";
+pub const METHOD_GET_CARPET_CLEAN_MODE: &'static str = "get_carpet_clean_mode";
+pub const METHOD_SET_CARPET_CLEAN_MODE: &'static str = "set_carpet_clean_mode";
+
+/// The maximum velocity of a remote control movement, in m/s
+pub const RC_MAX_VELOCITY: f64 = 0.3;
+/// The maximum rotation of a remote control movement, in degrees
+pub const RC_MAX_ROTATION: f64 = 179.0;
+/// The maximum duration of a remote control movement, in milliseconds
+pub const RC_MAX_DURATION: u32 = 5000;
 
 #[derive(Debug, Serialize)]
 pub struct EmptyJsonObject {}
 
+/// Commands which control the cleaning, and take no parameters
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControlCommand {
+    /// Start (or resume) cleaning
+    Start,
+    /// Pause cleaning
+    Pause,
+    /// Stop cleaning
+    Stop,
+    /// Return to the charging dock
+    Home,
+    /// Clean the area around the current position
+    Spot,
+    /// Play a sound, to help find the robot
+    Locate
+}
+
+/// A generic miio command, for any method name and parameters
+#[derive(Debug, Serialize)]
+pub struct Command {
+    pub id: u32,
+    pub method: String,
+    pub params: Value
+}
+
+/// A generic miio response, containing either a `result` or an `error`
+#[derive(Debug, Deserialize)]
+pub struct Response {
+    pub id: u32,
+    #[serde(default)]
+    pub result: Value,
+    #[serde(default)]
+    pub error: Option<ResponseError>
+}
+
+/// The `error` object of a miio response
+#[derive(Debug, PartialEq, Deserialize)]
+pub struct ResponseError {
+    pub code: i32,
+    pub message: String
+}
+
 #[derive(Debug, Serialize)]
 pub struct StatusCommand {
     pub id: u32,
@@ -13,40 +118,712 @@ pub struct StatusCommand {
     params: EmptyJsonObject
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct StatusResponse {
     pub id: u32,
     pub result: Vec<StatusResponseResult>
 }
 
-#[derive(Debug, PartialEq, Deserialize)]
+/// The result of the `get_status` method.
+///
+/// Only the fields reported by all known firmware versions are required; the rest default to `0` when missing.
+/// Fields which are only reported by some models (e.g. the S6, S7 and Q-series) are optional, and any other field
+/// is collected in a map of unknown fields.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
 pub struct StatusResponseResult {
+    #[serde(default)]
     msg_ver: u32,
+    #[serde(default)]
     msg_seq: u32,
+    /// State code, see `VacuumState`
     state: i32,
+    /// Battery level, in percent
     battery: u32,
+    /// Duration of the current (or last) cleaning, in seconds
     clean_time: u32,
+    /// Area of the current (or last) cleaning, in mm²
     clean_area: u32,
+    /// Error code, see `VacuumError`
     error_code: i32,
+    /// `1` if the robot has a map
+    #[serde(default)]
     map_present: i32,
+    /// `1` if the robot is currently cleaning
+    #[serde(default)]
     in_cleaning: i32,
+    /// `1` if the robot is returning to the dock
+    #[serde(default)]
     in_returning: i32,
+    /// `1` if the robot has not started a cleaning since it was last reset
+    #[serde(default)]
     in_fresh_state: i32,
+    /// `1` if the lab mode (e.g. no-go zones and virtual walls) is enabled
+    #[serde(default)]
     lab_status: i32,
+    /// Suction power, see `FanSpeed`
     fan_power: i32,
-    dnd_enabled: i32
+    /// `1` if the Do-Not-Disturb mode is enabled
+    #[serde(default)]
+    dnd_enabled: i32,
+    /// `1` if the water box is attached
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    water_box_status: Option<i32>,
+    /// Water flow of the mop
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    water_box_mode: Option<i32>,
+    /// `1` if the mop carriage is attached
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    water_box_carriage_status: Option<i32>,
+    /// Mop route mode (e.g. standard or deep)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    mop_mode: Option<i32>,
+    /// Type of the dock (e.g. charger only, or auto-empty dock)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    dock_type: Option<i32>,
+    /// Progress of the current cleaning, in percent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    clean_percent: Option<u32>,
+    /// `1` if the child lock is enabled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    lock_status: Option<i32>,
+    /// Any field which is not known to this library
+    #[serde(flatten)]
+    unknown_fields: BTreeMap<String, Value>
+}
+
+/// The result of the `miIO.info` method
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct DeviceInfo {
+    pub model: String,
+    pub fw_ver: String,
+    pub hw_ver: String,
+    pub mac: String,
+    #[serde(default)]
+    pub wifi_fw_ver: Option<String>,
+    #[serde(default)]
+    pub ap: Option<AccessPointInfo>,
+    #[serde(default)]
+    pub netif: Option<NetworkInterfaceInfo>,
+    #[serde(default)]
+    pub uid: Option<u64>,
+    /// Seconds since the device was powered on
+    #[serde(default)]
+    pub life: Option<u64>
+}
+
+/// The Wi-Fi access point to which the device is connected
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct AccessPointInfo {
+    pub ssid: String,
+    pub bssid: String,
+    pub rssi: i32
+}
+
+/// The network configuration of the device
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct NetworkInterfaceInfo {
+    #[serde(rename = "localIp")]
+    pub local_ip: String,
+    pub mask: String,
+    pub gw: String
+}
+
+/// The parameters of the `miIO.config_router` method
+#[derive(Debug, PartialEq, Serialize)]
+pub struct RouterConfig {
+    pub ssid: String,
+    pub passwd: String,
+    pub uid: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub country_domain: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tz: Option<String>
+}
+
+/// The result of the `get_consumable` method. Work times are in seconds.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ConsumableStatus {
+    pub main_brush_work_time: u32,
+    pub side_brush_work_time: u32,
+    pub filter_work_time: u32,
+    pub sensor_dirty_time: u32,
+    /// Work time of the water filter element, on robots which support mopping
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filter_element_work_time: Option<u32>,
+    /// Number of mop washes since the dock strainer was last cleaned, on robots with a washing dock
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strainer_work_times: Option<u32>,
+    /// Number of mop washes since the dock cleaning brush was last cleaned, on robots with a washing dock
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cleaning_brush_work_times: Option<u32>,
+    /// Number of bin emptyings, on robots with an auto-empty dock
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dust_collection_work_times: Option<u32>
+}
+
+/// The result of the `get_current_sound` method
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct CurrentSound {
+    /// Id of the voice pack which is being installed, or `0`
+    #[serde(default)]
+    pub sid_in_progress: u32,
+    /// Id of the installed voice pack. The default voice pack has id `1`.
+    pub sid_in_use: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sid_version: Option<u32>
+}
+
+/// The parameters of the `dnld_install_sound` method
+#[derive(Debug, PartialEq, Serialize)]
+pub struct SoundInstall {
+    /// URL of the voice pack, which must be reachable by the robot
+    pub url: String,
+    /// MD5 checksum of the voice pack, as a lowercase hex string
+    pub md5: String,
+    /// Id of the voice pack
+    pub sid: u32
+}
+
+/// The parameters of the `miIO.ota` method
+#[derive(Debug, PartialEq, Serialize)]
+pub struct OtaRequest {
+    pub mode: String,
+    /// `"1"` to install the firmware as soon as it is downloaded
+    pub install: String,
+    /// URL of the firmware image, which must be reachable by the robot
+    pub app_url: String,
+    /// MD5 checksum of the firmware image, as a lowercase hex string
+    pub file_md5: String,
+    pub proc: String
+}
+
+/// The result of the `get_sound_progress` method
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SoundProgress {
+    pub sid_in_progress: u32,
+    /// Installation progress, in percent
+    pub progress: u32,
+    state: i32,
+    /// Error code of a failed installation
+    pub error: i32
+}
+
+/// The result of the `get_clean_summary` method.
+///
+/// Older firmware replies with an array `[clean_time, clean_area, clean_count, [records]]`, while newer firmware
+/// replies with an object. Both shapes are accepted.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(from = "CleanSummaryRepr")]
+pub struct CleanSummary {
+    /// Total cleaning time, in seconds
+    pub clean_time: u64,
+    /// Total cleaned area, in mm²
+    pub clean_area: u64,
+    pub clean_count: u32,
+    /// Number of bin emptyings, on robots with an auto-empty dock
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dust_collection_count: Option<u32>,
+    /// Ids of the most recent clean records, which can be fetched with `get_clean_record`
+    pub records: Vec<u64>
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum CleanSummaryRepr {
+    Array(u64, u64, u32, #[serde(default)] Vec<u64>),
+    Object {
+        clean_time: u64,
+        clean_area: u64,
+        clean_count: u32,
+        #[serde(default)]
+        dust_collection_count: Option<u32>,
+        #[serde(default)]
+        records: Vec<u64>
+    }
+}
+
+/// A single clean record, as returned by the `get_clean_record` method.
+///
+/// Older firmware replies with an array `[begin, end, duration, area, error, complete, ...]`, while newer firmware
+/// replies with an object. Both shapes are accepted.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "CleanRecordRepr")]
+pub struct CleanRecord {
+    /// Start of the cleaning, as a UNIX timestamp
+    pub begin: u64,
+    /// End of the cleaning, as a UNIX timestamp
+    pub end: u64,
+    /// Duration of the cleaning, in seconds
+    pub duration: u64,
+    /// Cleaned area, in mm²
+    pub area: u64,
+    /// Error code, see `VacuumError`
+    pub error: i32,
+    pub complete: bool,
+    /// How the cleaning was started (e.g. from the app, the button, or a timer)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_type: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub clean_type: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finish_reason: Option<i32>
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum CleanRecordRepr {
+    Array(Vec<i64>),
+    Object {
+        begin: u64,
+        end: u64,
+        duration: u64,
+        area: u64,
+        error: i32,
+        /// `0`/`1` as reported by the device, or `false`/`true` as serialized by `CleanRecord`
+        complete: Value,
+        #[serde(default)]
+        start_type: Option<i32>,
+        #[serde(default)]
+        clean_type: Option<i32>,
+        #[serde(default)]
+        finish_reason: Option<i32>
+    }
+}
+
+/// The parameters of the `app_rc_move` method
+#[derive(Debug, PartialEq, Serialize)]
+pub struct RcMove {
+    /// Rotation, in radians
+    pub omega: f64,
+    /// Velocity, in m/s. Negative values move the robot backwards.
+    pub velocity: f64,
+    /// Sequence number, which has to increase with each movement of a remote control session
+    pub seqnum: u32,
+    /// Duration of the movement, in milliseconds
+    pub duration: u32
+}
+
+/// An entry of the `get_room_mapping` result, which is reported as `[segment_id, cloud_room_id]` or
+/// `[segment_id, cloud_room_id, room_type]`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "Vec<Value>", into = "Vec<Value>")]
+pub struct RoomMapping {
+    /// Id of the segment on the map, as used by `app_segment_clean`
+    pub segment_id: u32,
+    /// Id of the room in the vendor cloud
+    pub room_id: String,
+    pub room_type: Option<i32>
+}
+
+/// The result of the `get_multi_maps_list` method
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct MultiMapsList {
+    /// The maximum number of saved maps
+    pub max_multi_map: u32,
+    #[serde(default)]
+    pub max_bak_map: u32,
+    pub multi_map_count: u32,
+    #[serde(default)]
+    pub map_info: Vec<MapInfo>
+}
+
+/// A saved map, as listed by the `get_multi_maps_list` method
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct MapInfo {
+    /// Id of the map, as used by `load_multi_map`
+    #[serde(rename = "mapFlag")]
+    pub map_flag: u32,
+    /// Creation time of the map, as a UNIX timestamp
+    #[serde(default)]
+    pub add_time: u64,
+    #[serde(default)]
+    pub name: String
+}
+
+/// The parameters of the `app_segment_clean` method, when a repeat count is given
+#[derive(Debug, PartialEq, Serialize)]
+pub struct SegmentClean {
+    pub segments: Vec<u32>,
+    pub repeat: u32
+}
+
+impl MultiMapsList {
+    /// Find a saved map by its name (case-insensitive), or by its map flag
+    pub fn find(&self, name_or_flag: &str) -> Option<&MapInfo> {
+        self.map_info.iter().find(|m| m.name.to_lowercase() == name_or_flag.to_lowercase())
+            .or_else(|| self.map_info.iter().find(|m| m.map_flag.to_string() == name_or_flag.trim()))
+    }
+}
+
+impl ControlCommand {
+    /// The miio method name of the command
+    pub fn method(&self) -> &'static str {
+        match *self {
+            ControlCommand::Start => METHOD_APP_START,
+            ControlCommand::Pause => METHOD_APP_PAUSE,
+            ControlCommand::Stop => METHOD_APP_STOP,
+            ControlCommand::Home => METHOD_APP_CHARGE,
+            ControlCommand::Spot => METHOD_APP_SPOT,
+            ControlCommand::Locate => METHOD_FIND_ME,
+        }
+    }
+}
+
+impl Command {
+    pub fn new(cmdid: u32, method: &str, params: Value) -> Command {
+        return Command {
+            id: cmdid,
+            method: method.to_string(),
+            params
+        }
+    }
+}
+
+impl OtaRequest {
+    /// Create a request which downloads and installs the firmware image
+    pub fn new(app_url: &str, file_md5: &str) -> OtaRequest {
+        OtaRequest {
+            mode: "normal".to_string(),
+            install: "1".to_string(),
+            app_url: app_url.to_string(),
+            file_md5: file_md5.to_string(),
+            proc: "dnld install".to_string()
+        }
+    }
+}
+
+impl SoundProgress {
+    pub fn state(&self) -> SoundInstallState {
+        SoundInstallState::from(self.state)
+    }
+}
+
+impl ConsumableStatus {
+    /// The usage of the consumable since it was last reset (see `Consumable::interval` for the unit), or
+    /// `Option::None` if the robot does not report it
+    pub fn used(&self, consumable: Consumable) -> Option<u64> {
+        let used = match consumable {
+            Consumable::MainBrush => Some(self.main_brush_work_time),
+            Consumable::SideBrush => Some(self.side_brush_work_time),
+            Consumable::Filter => Some(self.filter_work_time),
+            Consumable::Sensor => Some(self.sensor_dirty_time),
+            Consumable::FilterElement => self.filter_element_work_time,
+            Consumable::Strainer => self.strainer_work_times,
+            Consumable::CleaningBrush => self.cleaning_brush_work_times,
+            Consumable::DustCollection => self.dust_collection_work_times,
+        };
+        used.map(u64::from)
+    }
+
+    /// The usage left until the consumable should be replaced (or cleaned, for the sensors and the dock parts)
+    pub fn remaining(&self, consumable: Consumable) -> Option<u64> {
+        self.used(consumable).map(|used| consumable.interval().saturating_sub(used))
+    }
+
+    /// The remaining life of the consumable, in percent
+    pub fn remaining_percent(&self, consumable: Consumable) -> Option<f64> {
+        self.remaining(consumable).map(|remaining| remaining as f64 * 100.0 / consumable.interval() as f64)
+    }
+}
+
+impl From<CleanSummaryRepr> for CleanSummary {
+    fn from(repr: CleanSummaryRepr) -> CleanSummary {
+        match repr {
+            CleanSummaryRepr::Array(clean_time, clean_area, clean_count, records) => CleanSummary {
+                clean_time, clean_area, clean_count, dust_collection_count: None, records
+            },
+            CleanSummaryRepr::Object { clean_time, clean_area, clean_count, dust_collection_count, records } => {
+                CleanSummary { clean_time, clean_area, clean_count, dust_collection_count, records }
+            }
+        }
+    }
+}
+
+impl CleanSummary {
+    /// The total cleaning time
+    pub fn total_time(&self) -> Duration {
+        Duration::from_secs(self.clean_time)
+    }
+
+    /// The total cleaned area, in m²
+    pub fn total_area(&self) -> f64 {
+        self.clean_area as f64 / 1_000_000.0
+    }
+}
+
+impl TryFrom<CleanRecordRepr> for CleanRecord {
+    type Error = String;
+
+    fn try_from(repr: CleanRecordRepr) -> Result<CleanRecord, String> {
+        match repr {
+            CleanRecordRepr::Array(a) => {
+                if a.len() < 6 {
+                    return Err(format!("clean record has {} fields, expected at least 6", a.len()));
+                }
+                Ok(CleanRecord {
+                    begin: a[0] as u64,
+                    end: a[1] as u64,
+                    duration: a[2] as u64,
+                    area: a[3] as u64,
+                    error: a[4] as i32,
+                    complete: a[5] != 0,
+                    start_type: a.get(6).map(|v| *v as i32),
+                    clean_type: a.get(7).map(|v| *v as i32),
+                    finish_reason: a.get(8).map(|v| *v as i32)
+                })
+            }
+            CleanRecordRepr::Object { begin, end, duration, area, error, complete, start_type, clean_type,
+                                      finish_reason } => {
+                Ok(CleanRecord {
+                    begin, end, duration, area, error,
+                    complete: complete.as_bool().unwrap_or_else(|| complete.as_i64().unwrap_or(0) != 0),
+                    start_type, clean_type, finish_reason
+                })
+            }
+        }
+    }
+}
+
+impl CleanRecord {
+    /// The duration of the cleaning
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs(self.duration)
+    }
+
+    /// The cleaned area, in m²
+    pub fn area(&self) -> f64 {
+        self.area as f64 / 1_000_000.0
+    }
+
+    /// The error the cleaning ended with, or `VacuumError::NoError`
+    pub fn error(&self) -> VacuumError {
+        VacuumError::from(self.error)
+    }
+}
+
+impl TryFrom<Vec<Value>> for RoomMapping {
+    type Error = String;
+
+    fn try_from(v: Vec<Value>) -> Result<RoomMapping, String> {
+        let segment_id = match v.first().and_then(|s| s.as_u64()) {
+            Some(segment_id) => segment_id as u32,
+            None => { return Err(format!("invalid room mapping {:?}", v)); }
+        };
+        let room_id = match v.get(1) {
+            Some(Value::String(room_id)) => room_id.clone(),
+            Some(Value::Number(room_id)) => room_id.to_string(),
+            _ => { return Err(format!("invalid room mapping {:?}", v)); }
+        };
+        let room_type = v.get(2).and_then(|t| t.as_i64()).map(|t| t as i32);
+        Ok(RoomMapping { segment_id, room_id, room_type })
+    }
+}
+
+impl From<RoomMapping> for Vec<Value> {
+    fn from(m: RoomMapping) -> Vec<Value> {
+        let mut v = vec!(Value::from(m.segment_id), Value::from(m.room_id));
+        if let Some(room_type) = m.room_type {
+            v.push(Value::from(room_type));
+        }
+        v
+    }
+}
+
+impl RcMove {
+    /// Create a remote control movement, checking that it is within the safety limits
+    ///
+    /// # Arguments
+    ///
+    /// `rotation` - rotation in degrees, between `-RC_MAX_ROTATION` and `RC_MAX_ROTATION`
+    /// `velocity` - velocity in m/s, between `-RC_MAX_VELOCITY` and `RC_MAX_VELOCITY`
+    /// `duration` - duration in milliseconds, at most `RC_MAX_DURATION`
+    /// `seqnum` - sequence number of the movement
+    ///
+    pub fn new(rotation: f64, velocity: f64, duration: u32, seqnum: u32) -> Result<RcMove, String> {
+        if rotation.is_nan() || rotation.abs() > RC_MAX_ROTATION {
+            return Err(format!("rotation {} is outside of -{}..{} degrees", rotation, RC_MAX_ROTATION, RC_MAX_ROTATION));
+        }
+        if velocity.is_nan() || velocity.abs() > RC_MAX_VELOCITY {
+            return Err(format!("velocity {} is outside of -{}..{} m/s", velocity, RC_MAX_VELOCITY, RC_MAX_VELOCITY));
+        }
+        if duration > RC_MAX_DURATION {
+            return Err(format!("duration {} is longer than {} ms", duration, RC_MAX_DURATION));
+        }
+        Ok(RcMove {
+            omega: (rotation.to_radians() * 10.0).round() / 10.0,
+            velocity,
+            seqnum,
+            duration
+        })
+    }
+
+    /// Create a remote control movement from a `Duration`, checking that it is within the safety limits
+    ///
+    /// # Arguments
+    ///
+    /// `rotation` - rotation in degrees, between `-RC_MAX_ROTATION` and `RC_MAX_ROTATION`
+    /// `velocity` - velocity in m/s, between `-RC_MAX_VELOCITY` and `RC_MAX_VELOCITY`
+    /// `duration` - duration of the movement, at most `RC_MAX_DURATION` milliseconds
+    /// `seqnum` - sequence number of the movement
+    ///
+    pub fn with_duration(rotation: f64, velocity: f64, duration: Duration, seqnum: u32) -> Result<RcMove, String> {
+        if duration > Duration::from_millis(RC_MAX_DURATION as u64) {
+            return Err(format!("duration {} ms is longer than {} ms", duration.as_millis(), RC_MAX_DURATION));
+        }
+        RcMove::new(rotation, velocity, duration.as_millis() as u32, seqnum)
+    }
+}
+
+impl StatusResponseResult {
+    /// The state of the robot
+    pub fn state(&self) -> VacuumState {
+        VacuumState::from(self.state)
+    }
+
+    /// The raw state code, as reported by the robot
+    pub fn state_code(&self) -> i32 {
+        self.state
+    }
+
+    /// The error the robot is in, or `VacuumError::NoError`
+    pub fn error(&self) -> VacuumError {
+        VacuumError::from(self.error_code)
+    }
+
+    /// The raw error code, as reported by the robot
+    pub fn error_code(&self) -> i32 {
+        self.error_code
+    }
+
+    /// The version of the status message format
+    pub fn msg_ver(&self) -> u32 {
+        self.msg_ver
+    }
+
+    /// The sequence number of the status message
+    pub fn msg_seq(&self) -> u32 {
+        self.msg_seq
+    }
+
+    /// The battery level, in percent
+    pub fn battery(&self) -> u32 {
+        self.battery
+    }
+
+    /// The duration of the current (or last) cleaning
+    pub fn clean_time(&self) -> Duration {
+        Duration::from_secs(self.clean_time as u64)
+    }
+
+    /// The area of the current (or last) cleaning, in m²
+    pub fn clean_area(&self) -> f64 {
+        self.clean_area as f64 / 1_000_000.0
+    }
+
+    /// Check if the robot has a map
+    pub fn map_present(&self) -> bool {
+        self.map_present != 0
+    }
+
+    /// Check if the robot is currently cleaning
+    pub fn in_cleaning(&self) -> bool {
+        self.in_cleaning != 0
+    }
+
+    /// Check if the robot is returning to the dock
+    pub fn in_returning(&self) -> bool {
+        self.in_returning != 0
+    }
+
+    /// Check if the robot has not started a cleaning since it was last reset
+    pub fn in_fresh_state(&self) -> bool {
+        self.in_fresh_state != 0
+    }
+
+    /// Check if the lab mode (e.g. no-go zones and virtual walls) is enabled
+    pub fn lab_status(&self) -> bool {
+        self.lab_status != 0
+    }
+
+    /// The suction power
+    pub fn fan_speed(&self) -> FanSpeed {
+        FanSpeed::from(self.fan_power)
+    }
+
+    /// The raw suction power, as reported by the robot
+    pub fn fan_power(&self) -> i32 {
+        self.fan_power
+    }
+
+    /// Check if the Do-Not-Disturb mode is enabled
+    pub fn dnd_enabled(&self) -> bool {
+        self.dnd_enabled != 0
+    }
+
+    /// Check if the water box is attached, if the robot supports mopping
+    pub fn water_box_attached(&self) -> Option<bool> {
+        self.water_box_status.map(|v| v != 0)
+    }
+
+    /// The water flow of the mop, if the robot supports mopping
+    pub fn water_box_mode(&self) -> Option<i32> {
+        self.water_box_mode
+    }
+
+    /// Check if the mop carriage is attached, if the robot supports mopping
+    pub fn water_box_carriage_attached(&self) -> Option<bool> {
+        self.water_box_carriage_status.map(|v| v != 0)
+    }
+
+    /// The mop route mode, if the robot supports mopping
+    pub fn mop_mode(&self) -> Option<i32> {
+        self.mop_mode
+    }
+
+    /// The type of the dock, if reported by the robot
+    pub fn dock_type(&self) -> Option<i32> {
+        self.dock_type
+    }
+
+    /// The progress of the current cleaning, in percent, if reported by the robot
+    pub fn clean_percent(&self) -> Option<u32> {
+        self.clean_percent
+    }
+
+    /// Check if the child lock is enabled, if the robot supports it
+    pub fn locked(&self) -> Option<bool> {
+        self.lock_status.map(|v| v != 0)
+    }
+
+    /// All the fields reported by the robot which are not known to this library
+    pub fn unknown_fields(&self) -> &BTreeMap<String, Value> {
+        &self.unknown_fields
+    }
 }
 
 impl StatusCommand {
     pub fn new(cmdid: u32) -> StatusCommand {
         return StatusCommand {
             id: cmdid,
-            method: METHOD_GET_STATUS_VAL.to_string(),
+            method: METHOD_GET_STATUS.to_string(),
             params: EmptyJsonObject{}
         }
     }
 }
 
+/// Check if a method result is `"ok"` or `["ok"]`, which is how the device acknowledges most commands
+///
+/// # Arguments
+/// `result` - the `result` value of a response
+///
+pub fn is_ok_result(result: &Value) -> bool {
+    match result {
+        Value::String(s) => s == "ok",
+        Value::Array(a) => a.len() == 1 && a[0] == "ok",
+        _ => false
+    }
+}
+
 /// Return the length of the substring which contains the last `}` in the given string
 ///
 /// This is useful because most `MiPacket`s coming from the RoboRock contain some junk bytes at the end of the
@@ -65,6 +842,8 @@ pub fn find_last_closing_bracket(buf: &str) -> usize {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::fanspeed::FanSpeedScheme;
+    use crate::zone::Zone;
     use serde_json::{Value, json};
     const GET_STATUS_RESPONSE_STR: &str = "{\"result\":[{
                                             \"msg_ver\":2,
@@ -92,6 +871,315 @@ mod tests {
         assert_eq!(serialized, "{\"id\":1,\"method\":\"get_status\",\"params\":{}}");
     }
 
+    #[test]
+    fn test_command() {
+        let cmd = Command::new(7, "app_start", json!([]));
+        let serialized = serde_json::to_string(&cmd).unwrap();
+        assert_eq!(serialized, "{\"id\":7,\"method\":\"app_start\",\"params\":[]}");
+    }
+
+    #[test]
+    fn test_response_result() {
+        let resp_str = "{\"result\":[\"ok\"],\"id\":7}\u{0}";
+        let end = find_last_closing_bracket(resp_str);
+        let resp: Response = serde_json::from_str(&resp_str[..end]).unwrap();
+        assert_eq!(resp.id, 7);
+        assert_eq!(resp.result, json!(["ok"]));
+        assert_eq!(resp.error, None);
+    }
+
+    #[test]
+    fn test_response_error() {
+        let resp_str = "{\"error\":{\"code\":-10000,\"message\":\"Method not found.\"},\"id\":8}";
+        let resp: Response = serde_json::from_str(resp_str).unwrap();
+        assert_eq!(resp.id, 8);
+        assert_eq!(resp.result, Value::Null);
+        assert_eq!(resp.error, Some(ResponseError { code: -10000, message: "Method not found.".to_string() }));
+    }
+
+    #[test]
+    fn test_control_command() {
+        let cmd = Command::new(4, ControlCommand::Home.method(), json!([]));
+        let serialized = serde_json::to_string(&cmd).unwrap();
+        assert_eq!(serialized, "{\"id\":4,\"method\":\"app_charge\",\"params\":[]}");
+        assert_eq!(ControlCommand::Locate.method(), "find_me");
+    }
+
+    #[test]
+    fn test_is_ok_result() {
+        assert!(is_ok_result(&json!(["ok"])));
+        assert!(is_ok_result(&json!("ok")));
+        assert!(!is_ok_result(&json!(["unknown_method"])));
+        assert!(!is_ok_result(&json!(0)));
+    }
+
+    #[test]
+    fn test_fan_speed() {
+        assert_eq!(FanSpeed::Quiet.fan_power(FanSpeedScheme::Percentage), 38);
+        assert_eq!(FanSpeed::Quiet.fan_power(FanSpeedScheme::Code), 101);
+        assert_eq!(FanSpeed::Mop.fan_power(FanSpeedScheme::Percentage), 105);
+        assert_eq!(FanSpeed::Custom(85).fan_power(FanSpeedScheme::Code), 85);
+        assert_eq!(FanSpeedScheme::detect(60), FanSpeedScheme::Percentage);
+        assert_eq!(FanSpeedScheme::detect(102), FanSpeedScheme::Code);
+        assert_eq!(FanSpeed::from_str("Turbo"), Ok(FanSpeed::Turbo));
+        assert_eq!(FanSpeed::from_str("gentle"), Ok(FanSpeed::Mop));
+        assert_eq!(FanSpeed::from_str("85"), Ok(FanSpeed::Custom(85)));
+        assert!(FanSpeed::from_str("loud").is_err());
+
+        let cmd = Command::new(9, METHOD_SET_CUSTOM_MODE, json!([FanSpeed::Max.fan_power(FanSpeedScheme::Code)]));
+        let serialized = serde_json::to_string(&cmd).unwrap();
+        assert_eq!(serialized, "{\"id\":9,\"method\":\"set_custom_mode\",\"params\":[104]}");
+    }
+
+    #[test]
+    fn test_consumable_response() {
+        let resp_str = "{\"result\":[{\"main_brush_work_time\":540000,\"side_brush_work_time\":720000,
+                            \"filter_work_time\":54000,\"filter_element_work_time\":0,
+                            \"sensor_dirty_time\":3600}],\"id\":10}";
+        let resp: Response = serde_json::from_str(resp_str).unwrap();
+        let consumables: Vec<ConsumableStatus> = serde_json::from_value(resp.result).unwrap();
+        let consumable = &consumables[0];
+        assert_eq!(consumable.used(Consumable::MainBrush), Some(540000));
+        assert_eq!(consumable.remaining(Consumable::MainBrush), Some(150 * 3600));
+        assert_eq!(consumable.remaining_percent(Consumable::MainBrush), Some(50.0));
+        assert_eq!(consumable.remaining(Consumable::SideBrush), Some(0));
+        assert_eq!(consumable.remaining_percent(Consumable::SideBrush), Some(0.0));
+        assert_eq!(consumable.remaining_percent(Consumable::Filter), Some(90.0));
+        assert_eq!(consumable.remaining(Consumable::Sensor), Some(29 * 3600));
+        assert_eq!(consumable.remaining_percent(Consumable::FilterElement), Some(100.0));
+        assert_eq!(consumable.remaining(Consumable::Strainer), None);
+
+        let resp_str = "{\"result\":[{\"main_brush_work_time\":0,\"side_brush_work_time\":0,
+                            \"filter_work_time\":0,\"sensor_dirty_time\":0,\"strainer_work_times\":30,
+                            \"cleaning_brush_work_times\":300,\"dust_collection_work_times\":15}],\"id\":10}";
+        let resp: Response = serde_json::from_str(resp_str).unwrap();
+        let consumables: Vec<ConsumableStatus> = serde_json::from_value(resp.result).unwrap();
+        assert_eq!(consumables[0].remaining(Consumable::Strainer), Some(120));
+        assert_eq!(consumables[0].remaining_percent(Consumable::CleaningBrush), Some(0.0));
+        assert_eq!(consumables[0].remaining_percent(Consumable::DustCollection), Some(75.0));
+        assert_eq!(Consumable::from_str("dust_collection"), Ok(Consumable::DustCollection));
+        assert_eq!(Consumable::Strainer.key(), "strainer_work_times");
+
+        let cmd = Command::new(11, METHOD_RESET_CONSUMABLE, json!([Consumable::Filter.key()]));
+        let serialized = serde_json::to_string(&cmd).unwrap();
+        assert_eq!(serialized, "{\"id\":11,\"method\":\"reset_consumable\",\"params\":[\"filter_work_time\"]}");
+    }
+
+    #[test]
+    fn test_clean_summary_array() {
+        let resp_str = "{\"result\":[174145,2410150000,82,[1488240000,1488153600,1488067200]],\"id\":13}";
+        let resp: Response = serde_json::from_str(resp_str).unwrap();
+        let summary: CleanSummary = serde_json::from_value(resp.result).unwrap();
+        assert_eq!(summary.total_time(), Duration::from_secs(174145));
+        assert_eq!(summary.total_area(), 2410.15);
+        assert_eq!(summary.clean_count, 82);
+        assert_eq!(summary.dust_collection_count, None);
+        assert_eq!(summary.records, vec!(1488240000, 1488153600, 1488067200));
+    }
+
+    #[test]
+    fn test_clean_summary_object() {
+        let resp_str = "{\"result\":{\"clean_time\":174145,\"clean_area\":2410150000,\"clean_count\":82,
+                            \"dust_collection_count\":5,\"records\":[1488240000,1488153600]},\"id\":14}";
+        let resp: Response = serde_json::from_str(resp_str).unwrap();
+        let summary: CleanSummary = serde_json::from_value(resp.result).unwrap();
+        assert_eq!(summary.clean_count, 82);
+        assert_eq!(summary.dust_collection_count, Some(5));
+        assert_eq!(summary.records, vec!(1488240000, 1488153600));
+    }
+
+    #[test]
+    fn test_clean_record_array() {
+        let resp_str = "{\"result\":[[1488347071,1488347123,16,0,0,0]],\"id\":15}";
+        let resp: Response = serde_json::from_str(resp_str).unwrap();
+        let records: Vec<CleanRecord> = serde_json::from_value(resp.result).unwrap();
+        assert_eq!(records[0], CleanRecord {
+            begin: 1488347071, end: 1488347123, duration: 16, area: 0, error: 0, complete: false,
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn test_clean_record_object() {
+        let resp_str = "{\"result\":[{\"begin\":1620033312,\"end\":1620035418,\"duration\":1942,
+                            \"area\":31812500,\"error\":0,\"complete\":1,\"start_type\":2,\"clean_type\":1,
+                            \"finish_reason\":56,\"dust_collection_status\":1}],\"id\":16}";
+        let resp: Response = serde_json::from_str(resp_str).unwrap();
+        let records: Vec<CleanRecord> = serde_json::from_value(resp.result).unwrap();
+        let record = &records[0];
+        assert_eq!(record.duration(), Duration::from_secs(1942));
+        assert_eq!(record.area(), 31.8125);
+        assert_eq!(record.error(), VacuumError::NoError);
+        assert!(record.complete);
+        assert_eq!(record.start_type, Some(2));
+        assert_eq!(record.finish_reason, Some(56));
+    }
+
+    #[test]
+    fn test_clean_record_roundtrip() {
+        let record = CleanRecord {
+            begin: 1620033312, end: 1620035418, duration: 1942, area: 31812500, error: 0, complete: true,
+            start_type: Some(2), ..Default::default()
+        };
+        let serialized = serde_json::to_string(&record).unwrap();
+        let deserialized: CleanRecord = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(deserialized, record);
+    }
+
+    #[test]
+    fn test_clean_record_invalid() {
+        let records: Result<Vec<CleanRecord>, _> = serde_json::from_value(json!([[1488347071, 1488347123]]));
+        assert!(records.is_err());
+    }
+
+    #[test]
+    fn test_zoned_clean_command() {
+        let zones = [Zone::new(25000, 22000, 28500, 25000, 2), Zone::new(23000, 25500, 24500, 31000, 1)];
+        let params: Vec<[u32; 5]> = zones.iter().map(|z| z.to_params()).collect();
+        let cmd = Command::new(17, METHOD_APP_ZONED_CLEAN, json!(params));
+        let serialized = serde_json::to_string(&cmd).unwrap();
+        assert_eq!(serialized, "{\"id\":17,\"method\":\"app_zoned_clean\",\"params\":\
+                                [[25000,22000,28500,25000,2],[23000,25500,24500,31000,1]]}");
+    }
+
+    #[test]
+    fn test_rc_move() {
+        let rc_move = RcMove::new(90.0, 0.2, 1500, 3).unwrap();
+        assert_eq!(rc_move.omega, 1.6);
+        let cmd = Command::new(18, METHOD_APP_RC_MOVE, json!([rc_move]));
+        let serialized = serde_json::to_string(&cmd).unwrap();
+        assert_eq!(serialized, "{\"id\":18,\"method\":\"app_rc_move\",\"params\":\
+                                [{\"duration\":1500,\"omega\":1.6,\"seqnum\":3,\"velocity\":0.2}]}");
+
+        assert!(RcMove::new(-179.0, -0.3, 5000, 1).is_ok());
+        assert!(RcMove::new(180.0, 0.0, 1500, 1).is_err());
+        assert!(RcMove::new(0.0, 0.31, 1500, 1).is_err());
+        assert!(RcMove::new(0.0, f64::NAN, 1500, 1).is_err());
+        assert!(RcMove::new(0.0, 0.1, 5001, 1).is_err());
+        assert!(RcMove::new(f64::NAN, 0.1, 1500, 1).is_err());
+
+        assert_eq!(RcMove::with_duration(0.0, 0.1, Duration::from_millis(1500), 1).unwrap().duration, 1500);
+        assert!(RcMove::with_duration(0.0, 0.1, Duration::from_millis(5001), 1).is_err());
+        // would wrap around to 704 ms as a u32 number of milliseconds
+        assert!(RcMove::with_duration(0.0, 0.1, Duration::from_secs(4294968), 1).is_err());
+    }
+
+    #[test]
+    fn test_room_mapping() {
+        let resp_str = "{\"result\":[[16,\"2853457\"],[17,\"2853452\",14],[18,2853443]],\"id\":19}";
+        let resp: Response = serde_json::from_str(resp_str).unwrap();
+        let mapping: Vec<RoomMapping> = serde_json::from_value(resp.result).unwrap();
+        assert_eq!(mapping, vec!(
+            RoomMapping { segment_id: 16, room_id: "2853457".to_string(), room_type: None },
+            RoomMapping { segment_id: 17, room_id: "2853452".to_string(), room_type: Some(14) },
+            RoomMapping { segment_id: 18, room_id: "2853443".to_string(), room_type: None },
+        ));
+        assert!(serde_json::from_value::<Vec<RoomMapping>>(json!([["a", "b"]])).is_err());
+
+        let serialized = serde_json::to_string(&mapping).unwrap();
+        assert_eq!(serialized, "[[16,\"2853457\"],[17,\"2853452\",14],[18,\"2853443\"]]");
+        assert_eq!(serde_json::from_str::<Vec<RoomMapping>>(&serialized).unwrap(), mapping);
+    }
+
+    #[test]
+    fn test_segment_clean_command() {
+        let cmd = Command::new(20, METHOD_APP_SEGMENT_CLEAN, json!([16, 17]));
+        let serialized = serde_json::to_string(&cmd).unwrap();
+        assert_eq!(serialized, "{\"id\":20,\"method\":\"app_segment_clean\",\"params\":[16,17]}");
+
+        let params = SegmentClean { segments: vec!(16, 17), repeat: 2 };
+        let cmd = Command::new(21, METHOD_APP_SEGMENT_CLEAN, json!([params]));
+        let serialized = serde_json::to_string(&cmd).unwrap();
+        assert_eq!(serialized, "{\"id\":21,\"method\":\"app_segment_clean\",\"params\":\
+                                [{\"repeat\":2,\"segments\":[16,17]}]}");
+    }
+
+    #[test]
+    fn test_sound_payloads() {
+        let resp_str = "{\"result\":[{\"sid_in_progress\":0,\"sid_in_use\":3}],\"id\":22}";
+        let resp: Response = serde_json::from_str(resp_str).unwrap();
+        let current: Vec<CurrentSound> = serde_json::from_value(resp.result).unwrap();
+        assert_eq!(current[0], CurrentSound { sid_in_progress: 0, sid_in_use: 3, sid_version: None });
+
+        let resp_str = "{\"result\":[{\"sid_in_progress\":10000,\"progress\":45,\"state\":1,\"error\":0}],\"id\":23}";
+        let resp: Response = serde_json::from_str(resp_str).unwrap();
+        let progress: Vec<SoundProgress> = serde_json::from_value(resp.result).unwrap();
+        assert_eq!(progress[0].progress, 45);
+        assert_eq!(progress[0].state(), SoundInstallState::Downloading);
+        assert!(!progress[0].state().is_done());
+
+        let install = SoundInstall { url: "http://192.168.8.2:8000/voice.pkg".to_string(),
+                                     md5: "5d41402abc4b2a76b9719d911017c592".to_string(), sid: 10000 };
+        let cmd = Command::new(24, METHOD_DNLD_INSTALL_SOUND, json!(install));
+        let serialized = serde_json::to_string(&cmd).unwrap();
+        assert_eq!(serialized, "{\"id\":24,\"method\":\"dnld_install_sound\",\"params\":\
+                                {\"md5\":\"5d41402abc4b2a76b9719d911017c592\",\"sid\":10000,\
+                                \"url\":\"http://192.168.8.2:8000/voice.pkg\"}}");
+    }
+
+    #[test]
+    fn test_ota_command() {
+        let request = OtaRequest::new("http://192.168.8.2:8000/v11_002020.pkg", "5d41402abc4b2a76b9719d911017c592");
+        let cmd = Command::new(25, METHOD_OTA, serde_json::to_value(&request).unwrap());
+        let serialized = serde_json::to_string(&cmd).unwrap();
+        assert_eq!(serialized, "{\"id\":25,\"method\":\"miIO.ota\",\"params\":\
+                                {\"app_url\":\"http://192.168.8.2:8000/v11_002020.pkg\",\
+                                \"file_md5\":\"5d41402abc4b2a76b9719d911017c592\",\"install\":\"1\",\
+                                \"mode\":\"normal\",\"proc\":\"dnld install\"}}");
+    }
+
+    #[test]
+    fn test_multi_maps_list() {
+        let resp_str = "{\"result\":[{\"max_multi_map\":4,\"max_bak_map\":1,\"multi_map_count\":2,\"map_info\":[\
+                        {\"mapFlag\":0,\"add_time\":1620000000,\"length\":12,\"name\":\"Ground floor\",\
+                        \"bak_maps\":[{\"mapFlag\":4,\"add_time\":1619000000}]},\
+                        {\"mapFlag\":1,\"add_time\":1620100000,\"length\":11,\"name\":\"First floor\"}]}],\"id\":26}";
+        let resp: Response = serde_json::from_str(resp_str).unwrap();
+        let maps: Vec<MultiMapsList> = serde_json::from_value(resp.result).unwrap();
+        assert_eq!(maps[0].multi_map_count, 2);
+        assert_eq!(maps[0].map_info[1], MapInfo { map_flag: 1, add_time: 1620100000, name: "First floor".to_string() });
+        assert_eq!(maps[0].find("ground FLOOR").map(|m| m.map_flag), Some(0));
+        assert_eq!(maps[0].find("1").map(|m| m.name.as_str()), Some("First floor"));
+        assert!(maps[0].find("Attic").is_none());
+    }
+
+    #[test]
+    fn test_info_response() {
+        let info_str = "{\"result\":{\"hw_ver\":\"Linux\",\"fw_ver\":\"3.5.4_004004\",
+                            \"ap\":{\"ssid\":\"home\",\"bssid\":\"AA:BB:CC:DD:EE:FF\",\"rssi\":-46,\"freq\":2412},
+                            \"netif\":{\"localIp\":\"192.168.1.5\",\"mask\":\"255.255.255.0\",\"gw\":\"192.168.1.1\"},
+                            \"model\":\"roborock.vacuum.s5\",\"mac\":\"78:11:DC:00:11:22\",
+                            \"token\":\"00000000000000000000000000000000\",\"life\":83924,\"uid\":1234567,
+                            \"wifi_fw_ver\":\"v3.3.9-g3e3d7b8\",\"mmfree\":230268,\"miio_ver\":\"0.0.5\"},
+                        \"id\":2}";
+        let resp: Response = serde_json::from_str(info_str).unwrap();
+        let info: DeviceInfo = serde_json::from_value(resp.result).unwrap();
+        assert_eq!(info.model, "roborock.vacuum.s5");
+        assert_eq!(info.fw_ver, "3.5.4_004004");
+        assert_eq!(info.hw_ver, "Linux");
+        assert_eq!(info.mac, "78:11:DC:00:11:22");
+        assert_eq!(info.wifi_fw_ver, Some("v3.3.9-g3e3d7b8".to_string()));
+        assert_eq!(info.ap, Some(AccessPointInfo {
+            ssid: "home".to_string(), bssid: "AA:BB:CC:DD:EE:FF".to_string(), rssi: -46 }));
+        assert_eq!(info.netif, Some(NetworkInterfaceInfo {
+            local_ip: "192.168.1.5".to_string(), mask: "255.255.255.0".to_string(), gw: "192.168.1.1".to_string() }));
+        assert_eq!(info.uid, Some(1234567));
+        assert_eq!(info.life, Some(83924));
+    }
+
+    #[test]
+    fn test_router_config() {
+        let config = RouterConfig {
+            ssid: "home".to_string(),
+            passwd: "secret".to_string(),
+            uid: 1234567,
+            country_domain: Some("de".to_string()),
+            tz: None
+        };
+        let serialized = serde_json::to_string(&config).unwrap();
+        assert_eq!(serialized, "{\"ssid\":\"home\",\"passwd\":\"secret\",\"uid\":1234567,\"country_domain\":\"de\"}");
+    }
+
     #[test]
     fn test_get_status_response() {
         let end = find_last_closing_bracket(GET_STATUS_RESPONSE_STR);
@@ -110,7 +1198,8 @@ mod tests {
             in_fresh_state: 1,
             lab_status: 1,
             fan_power: 60,
-            dnd_enabled: 0
+            dnd_enabled: 0,
+            ..Default::default()
         };
         let status_response_compare = StatusResponse {
             id: 5,
@@ -119,4 +1208,96 @@ mod tests {
         assert_eq!(status_response.id, status_response_compare.id);
         assert_eq!(status_response.result[0], status_response_compare.result[0]);
     }
+
+    #[test]
+    fn test_get_status_response_extended() {
+        let status_str = "{\"result\":[{\"msg_ver\":2,\"msg_seq\":1101,\"state\":18,\"battery\":87,
+                            \"clean_time\":945,\"clean_area\":14627500,\"error_code\":0,\"map_present\":1,
+                            \"in_cleaning\":3,\"in_returning\":0,\"in_fresh_state\":0,\"lab_status\":3,
+                            \"water_box_status\":1,\"fan_power\":102,\"dnd_enabled\":0,\"map_status\":7,
+                            \"is_locating\":0,\"lock_status\":0,\"water_box_mode\":202,
+                            \"water_box_carriage_status\":1,\"mop_forbidden_enable\":1,\"dock_type\":3,
+                            \"clean_percent\":42,\"mop_mode\":300}],\"id\":12}";
+        let status_response: StatusResponse = serde_json::from_str(status_str).unwrap();
+        let status = &status_response.result[0];
+        assert_eq!(status.state(), VacuumState::SegmentCleaning);
+        assert_eq!(status.water_box_attached(), Some(true));
+        assert_eq!(status.water_box_mode(), Some(202));
+        assert_eq!(status.water_box_carriage_attached(), Some(true));
+        assert_eq!(status.mop_mode(), Some(300));
+        assert_eq!(status.dock_type(), Some(3));
+        assert_eq!(status.clean_percent(), Some(42));
+        assert_eq!(status.locked(), Some(false));
+        assert_eq!(status.unknown_fields().get("map_status"), Some(&json!(7)));
+        assert_eq!(status.unknown_fields().get("mop_forbidden_enable"), Some(&json!(1)));
+        assert_eq!(status.unknown_fields().len(), 3);
+    }
+
+    #[test]
+    fn test_get_status_response_minimal() {
+        let status_str = "{\"result\":[{\"state\":3,\"battery\":52,\"clean_time\":0,\"clean_area\":0,
+                            \"error_code\":0,\"fan_power\":60}],\"id\":1}";
+        let status_response: StatusResponse = serde_json::from_str(status_str).unwrap();
+        let status = &status_response.result[0];
+        assert_eq!(status.state(), VacuumState::Idle);
+        assert!(!status.lab_status());
+        assert_eq!(status.water_box_attached(), None);
+        assert!(status.unknown_fields().is_empty());
+    }
+
+    #[test]
+    fn test_status_state() {
+        let end = find_last_closing_bracket(GET_STATUS_RESPONSE_STR);
+        let status_response: StatusResponse = serde_json::from_str(&GET_STATUS_RESPONSE_STR[..end]).unwrap();
+        assert_eq!(status_response.result[0].state(), VacuumState::Charging);
+        assert_eq!(status_response.result[0].state_code(), 8);
+        assert_eq!(VacuumState::from(17), VacuumState::ZonedCleaning);
+        assert_eq!(VacuumState::from(6).to_string(), "Returning home");
+        assert_eq!(VacuumState::from(42), VacuumState::Unknown(42));
+        assert_eq!(VacuumState::Unknown(42).code(), 42);
+        assert_eq!(VacuumState::SegmentCleaning.code(), 18);
+    }
+
+    #[test]
+    fn test_status_accessors() {
+        let end = find_last_closing_bracket(GET_STATUS_RESPONSE_STR);
+        let status_response: StatusResponse = serde_json::from_str(&GET_STATUS_RESPONSE_STR[..end]).unwrap();
+        let status = &status_response.result[0];
+        assert_eq!(status.battery(), 100);
+        assert_eq!(status.clean_time(), Duration::from_secs(2154));
+        assert_eq!(status.clean_area(), 35.6925);
+        assert!(status.map_present());
+        assert!(!status.in_cleaning());
+        assert!(!status.in_returning());
+        assert!(status.in_fresh_state());
+        assert!(status.lab_status());
+        assert_eq!(status.fan_speed(), FanSpeed::Balanced);
+        assert_eq!(status.fan_power(), 60);
+        assert!(!status.dnd_enabled());
+        assert_eq!(FanSpeed::from(104), FanSpeed::Max);
+        assert_eq!(FanSpeed::from(42), FanSpeed::Custom(42));
+    }
+
+    #[test]
+    fn test_status_serialize() {
+        let end = find_last_closing_bracket(GET_STATUS_RESPONSE_STR);
+        let status_response: StatusResponse = serde_json::from_str(&GET_STATUS_RESPONSE_STR[..end]).unwrap();
+        let serialized = serde_json::to_value(&status_response).unwrap();
+        assert_eq!(serialized["id"], json!(5));
+        assert_eq!(serialized["result"][0]["clean_area"], json!(35692500));
+        assert_eq!(serialized["result"][0]["fan_power"], json!(60));
+    }
+
+    #[test]
+    fn test_status_error() {
+        let end = find_last_closing_bracket(GET_STATUS_RESPONSE_STR);
+        let status_response: StatusResponse = serde_json::from_str(&GET_STATUS_RESPONSE_STR[..end]).unwrap();
+        assert_eq!(status_response.result[0].error(), VacuumError::NoError);
+        assert!(!status_response.result[0].error().is_error());
+        assert_eq!(VacuumError::from(5), VacuumError::MainBrushJammed);
+        assert_eq!(VacuumError::from(5).to_string(), "Main brush jammed");
+        assert!(!VacuumError::from(9).remedy().is_empty());
+        assert_eq!(VacuumError::from(20), VacuumError::Unknown(20));
+        assert_eq!(VacuumError::OnCarpet.code(), 28);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,171 @@
+//! Over-the-air firmware updates with the `miIO.ota` method, and the update state reported by the
+//! `miIO.get_ota_state` method.
+//!
+//! An interrupted update can leave the robot unusable, so updates are only started while the robot is docked and
+//! sufficiently charged (see `check_ready`).
+
+use crate::miiopayloads::StatusResponseResult;
+use crate::vacuumstate::VacuumState;
+use std::fmt;
+
+/// The lowest battery level (in percent) at which an update is started
+pub const MIN_BATTERY: u32 = 50;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OtaState {
+    /// No update is in progress
+    Idle,
+    Downloading,
+    Downloaded,
+    Installing,
+    Installed,
+    Failed,
+    /// The robot can not be updated at the moment, e.g. because it is cleaning
+    Busy,
+    /// A state which is not known to this library
+    Unknown(String),
+}
+
+impl OtaState {
+    /// The state name used by the device
+    pub fn name(&self) -> &str {
+        match self {
+            OtaState::Idle => "idle",
+            OtaState::Downloading => "downloading",
+            OtaState::Downloaded => "downloaded",
+            OtaState::Installing => "installing",
+            OtaState::Installed => "installed",
+            OtaState::Failed => "failed",
+            OtaState::Busy => "busy",
+            OtaState::Unknown(name) => name,
+        }
+    }
+
+    /// Return `true` if the update has ended, either successfully or not
+    pub fn is_done(&self) -> bool {
+        matches!(self, OtaState::Installed | OtaState::Failed | OtaState::Busy)
+    }
+}
+
+/// Follows the states reported after `miIO.ota` was sent, to tell when the update has ended
+#[derive(Debug, Default)]
+pub struct OtaTracker {
+    started: bool,
+}
+
+impl OtaTracker {
+    /// Record a reported state, and return the final state if the update has ended
+    ///
+    /// A robot which is still idle at the first poll, without any progress, did not accept the update: the final
+    /// state is then `OtaState::Idle`. Once the update has started, going back to idle means it was abandoned, which
+    /// is reported as `OtaState::Failed`.
+    ///
+    /// # Arguments
+    ///
+    /// `state` - the state from the latest `miIO.get_ota_state` response
+    /// `progress` - the progress from the latest `miIO.get_ota_progress` response, in percent
+    ///
+    pub fn update(&mut self, state: OtaState, progress: u32) -> Option<OtaState> {
+        match state {
+            OtaState::Idle if self.started || progress > 0 => Some(OtaState::Failed),
+            OtaState::Idle => Some(OtaState::Idle),
+            state if state.is_done() => Some(state),
+            _ => {
+                self.started = true;
+                None
+            }
+        }
+    }
+}
+
+/// Check that the robot can be safely updated: it must be docked, and its battery level must be at least
+/// `min_battery` percent
+///
+/// # Arguments
+///
+/// `status` - the current status of the robot
+/// `min_battery` - the lowest battery level, in percent (see `MIN_BATTERY`)
+///
+pub fn check_ready(status: &StatusResponseResult, min_battery: u32) -> Result<(), String> {
+    match status.state() {
+        VacuumState::Charging | VacuumState::ChargingComplete => {}
+        state => { return Err(format!("the robot is not docked ({})", state)); }
+    }
+    if status.battery() < min_battery {
+        return Err(format!("the battery level is {}%, at least {}% is needed", status.battery(), min_battery));
+    }
+    Ok(())
+}
+
+impl From<&str> for OtaState {
+    fn from(name: &str) -> OtaState {
+        match name {
+            "idle" => OtaState::Idle,
+            "downloading" => OtaState::Downloading,
+            "downloaded" => OtaState::Downloaded,
+            "installing" => OtaState::Installing,
+            "installed" => OtaState::Installed,
+            "failed" => OtaState::Failed,
+            "busy" => OtaState::Busy,
+            name => OtaState::Unknown(name.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for OtaState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OtaState::Idle => f.write_str("Idle"),
+            OtaState::Downloading => f.write_str("Downloading"),
+            OtaState::Downloaded => f.write_str("Downloaded"),
+            OtaState::Installing => f.write_str("Installing"),
+            OtaState::Installed => f.write_str("Installed"),
+            OtaState::Failed => f.write_str("Failed"),
+            OtaState::Busy => f.write_str("Busy"),
+            OtaState::Unknown(name) => f.write_fmt(format_args!("Unknown ({})", name)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(state: i32, battery: u32) -> StatusResponseResult {
+        serde_json::from_value(serde_json::json!({"state": state, "battery": battery, "clean_time": 0,
+                                                  "clean_area": 0, "error_code": 0, "fan_power": 102})).unwrap()
+    }
+
+    #[test]
+    fn test_check_ready() {
+        assert!(check_ready(&status(8, 80), MIN_BATTERY).is_ok());
+        assert!(check_ready(&status(100, 100), MIN_BATTERY).is_ok());
+        assert!(check_ready(&status(8, 49), MIN_BATTERY).is_err());
+        assert!(check_ready(&status(5, 100), MIN_BATTERY).is_err());
+        assert!(check_ready(&status(3, 100), MIN_BATTERY).is_err());
+    }
+
+    #[test]
+    fn test_ota_state() {
+        assert_eq!(OtaState::from("downloading"), OtaState::Downloading);
+        assert!(!OtaState::from("installing").is_done());
+        assert!(OtaState::from("failed").is_done());
+        assert_eq!(OtaState::from("rebooting"), OtaState::Unknown("rebooting".to_string()));
+        assert_eq!(OtaState::Unknown("rebooting".to_string()).name(), "rebooting");
+    }
+
+    #[test]
+    fn test_ota_tracker() {
+        let mut tracker = OtaTracker::default();
+        assert_eq!(tracker.update(OtaState::Downloading, 10), None);
+        assert_eq!(tracker.update(OtaState::Installing, 100), None);
+        assert_eq!(tracker.update(OtaState::Installed, 100), Some(OtaState::Installed));
+
+        let mut tracker = OtaTracker::default();
+        assert_eq!(tracker.update(OtaState::Downloading, 40), None);
+        assert_eq!(tracker.update(OtaState::Idle, 0), Some(OtaState::Failed));
+
+        assert_eq!(OtaTracker::default().update(OtaState::Idle, 0), Some(OtaState::Idle));
+        assert_eq!(OtaTracker::default().update(OtaState::Busy, 0), Some(OtaState::Busy));
+    }
+}
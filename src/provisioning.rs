@@ -0,0 +1,141 @@
+//! Implementation for the provisioning functionality of the miio protocol, specifically for the Xiaomi RoboRock S5
+//! vacuum cleaner.
+//!
+//! A robot which is in "provisioning mode" (i.e. the user reset its wifi connection) offers a wifi Access Point, and
+//! hands out its token in the `md5` field of the discovery response (see the `discovery` module). This token can be
+//! used to send the `miIO.config_router` method, which tells the robot which wifi network to connect to.
+//!
+//! Once the robot connects to the given network, the provisioning token is no longer valid.
+//!
+
+use crate::{discovery, deviceinfo};
+use crate::device::Device;
+use crate::miiopayloads::{RouterConfig, METHOD_CONFIG_ROUTER, is_ok_result};
+use crate::provisioning::Error::*;
+use std::net::{UdpSocket, Ipv4Addr};
+use std::error::Error as StdError;
+use std::fmt;
+
+#[derive(Debug)]
+pub enum Error {
+    Discovery(discovery::Error),
+    Command(deviceinfo::Error),
+    /// The discovered device did not hand out a token, i.e. it is not in provisioning mode
+    NotProvisioning(Ipv4Addr),
+    /// The device replied to `miIO.config_router` with something other than `ok`
+    Rejected(String),
+}
+
+/// Return the provisioning token contained in a discovery response, or `Option::None` if the device is not in
+/// provisioning mode.
+///
+/// # Arguments
+///
+/// `response` - a discovery response
+///
+pub fn provisioning_token(response: &discovery::Response) -> Option<[u8; 16]> {
+    let md5 = response.packet.md5;
+    if md5.iter().all(|b| *b == 0) || md5.iter().all(|b| *b == 0xff) {
+        None
+    } else {
+        Some(md5)
+    }
+}
+
+/// Tell a device which is in provisioning mode to connect to the given wifi network
+///
+/// # Arguments
+///
+/// `device` - a session with the device, established with the provisioning token
+/// `ssid` - name of the wifi network
+/// `passwd` - password of the wifi network
+/// `uid` - id of the user account to which the device is bound
+/// `country_domain` - optional cloud server region, e.g. `de`, `us`, `cn`
+/// `tz` - optional time zone name, e.g. `Europe/Berlin`
+///
+pub fn configure_router(device: &mut Device, ssid: &str, passwd: &str, uid: u64, country_domain: Option<&str>,
+                        tz: Option<&str>) -> Result<(), Error>
+{
+    let config = RouterConfig {
+        ssid: ssid.to_string(),
+        passwd: passwd.to_string(),
+        uid,
+        country_domain: country_domain.map(|s| s.to_string()),
+        tz: tz.map(|s| s.to_string())
+    };
+    let params = match serde_json::to_value(&config) {
+        Ok(params) => params,
+        Err(e) => { return Err(Command(deviceinfo::Error::Parse(e.to_string()))); }
+    };
+
+    match device.send_command(METHOD_CONFIG_ROUTER, params) {
+        Ok(result) => {
+            if is_ok_result(&result) {
+                Ok(())
+            } else {
+                Err(Rejected(result.to_string()))
+            }
+        }
+        Err(e) => Err(Command(e))
+    }
+}
+
+/// Discover a device which is in provisioning mode, capture its token, and tell it to connect to the given wifi
+/// network. Returns the IP of the device which was configured.
+///
+/// # Arguments
+///
+/// `socket` - UDP socket on which to discover and configure the device
+/// `dip_opt` - Optional device address. If this argument is `Option::None`, then the discovery request will be
+///         broadcast, and the first device which is in provisioning mode will be configured.
+///
+/// See `configure_router` for a description of the other arguments.
+///
+pub fn provision(socket: UdpSocket, dip_opt: Option<Ipv4Addr>, ssid: &str, passwd: &str, uid: u64,
+                 country_domain: Option<&str>, tz: Option<&str>) -> Result<Ipv4Addr, Error>
+{
+    let discovery_socket = match socket.try_clone() {
+        Ok(s) => s,
+        Err(e) => { return Err(Discovery(discovery::Error::Socket(e.to_string()))); }
+    };
+    let responses = match discovery::discover(discovery_socket, dip_opt) {
+        Ok(responses) => responses,
+        Err(e) => { return Err(Discovery(e)); }
+    };
+
+    let (dip, token) = match responses.iter()
+        .find_map(|r| provisioning_token(r).map(|token| (r.ip, token))) {
+        Some(found) => found,
+        None => { return Err(NotProvisioning(responses[0].ip)); }
+    };
+
+    let mut device = match Device::connect(socket, dip, &token) {
+        Ok(device) => device,
+        Err(e) => { return Err(Command(e)); }
+    };
+    configure_router(&mut device, ssid, passwd, uid, country_domain, tz)?;
+
+    Ok(dip)
+}
+
+impl StdError for Error {
+    fn description(&self) -> &str {
+        match &*self {
+            Error::Discovery(_e) => "Discovery error",
+            Error::Command(_e) => "Command error",
+            Error::NotProvisioning(_ip) => "Device is not in provisioning mode",
+            Error::Rejected(_e) => "Configuration rejected"
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &*self {
+            Error::Discovery(e) => f.write_fmt(format_args!("Discovery error: {}", e)),
+            Error::Command(e) => f.write_fmt(format_args!("{}", e)),
+            Error::NotProvisioning(ip) => f.write_fmt(format_args!("Device {} is not in provisioning mode", ip)),
+            Error::Rejected(e) => f.write_fmt(format_args!("Configuration rejected by device: {}", e)),
+        }
+    }
+}
@@ -0,0 +1,754 @@
+//! Rendering of parsed maps (see `map`) to PNG and SVG images.
+//!
+//! The image is drawn with north (the highest `y` coordinate) at the top, with one map pixel for each `scale`
+//! pixels of the output, and can be rotated clockwise in steps of 90 degrees. Each kind of map element is drawn on
+//! its own layer, and layers can be turned off (see `Layers`).
+
+use crate::map::{RRMap, MapImage, Pixel, Point, PIXEL_SIZE};
+use crate::render::Error::*;
+use std::error::Error as StdError;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+type Rgba = [u8; 4];
+
+const COLOR_FLOOR: Rgba = [0x56, 0xaf, 0xfc, 0xff];
+const COLOR_WALL: Rgba = [0x3c, 0x3c, 0x3c, 0xff];
+const COLOR_OBSTACLE: Rgba = [0x80, 0x80, 0x80, 0xff];
+const COLOR_SCANNED: Rgba = [0xd8, 0xe4, 0xf0, 0xff];
+const COLOR_PATH: Rgba = [0xff, 0xff, 0xff, 0xff];
+const COLOR_ROBOT: Rgba = [0xff, 0x6a, 0x00, 0xff];
+const COLOR_CHARGER: Rgba = [0x2e, 0xcc, 0x71, 0xff];
+const COLOR_NO_GO: Rgba = [0xe7, 0x4c, 0x3c, 0x60];
+const COLOR_NO_GO_BORDER: Rgba = [0xe7, 0x4c, 0x3c, 0xff];
+const COLOR_NO_MOP: Rgba = [0x9b, 0x59, 0xb6, 0x60];
+const COLOR_NO_MOP_BORDER: Rgba = [0x9b, 0x59, 0xb6, 0xff];
+const COLOR_VIRTUAL_WALL: Rgba = [0xe7, 0x4c, 0x3c, 0xff];
+
+/// Colors of the segments, which are picked by segment id
+const SEGMENT_COLORS: [Rgba; 8] = [
+    [0xf1, 0xc4, 0x0f, 0xff],
+    [0x1a, 0xbc, 0x9c, 0xff],
+    [0xe6, 0x7e, 0x22, 0xff],
+    [0x34, 0x98, 0xdb, 0xff],
+    [0xa5, 0x69, 0xbd, 0xff],
+    [0x2e, 0xcc, 0x71, 0xff],
+    [0xec, 0x70, 0x63, 0xff],
+    [0x5d, 0x6d, 0x7e, 0xff],
+];
+
+/// Radius of the robot and the charger markers, in map pixels
+const MARKER_RADIUS: f64 = 3.0;
+
+/// Width of the path and of the virtual walls, in map pixels
+const LINE_WIDTH: f64 = 0.5;
+
+/// The largest scale, which renders a full 1024x1024 pixel map to a 32768x32768 pixel image
+pub const MAX_SCALE: u32 = 32;
+
+#[derive(Debug)]
+pub enum Error {
+    /// The map has no image block, so its extent is unknown
+    NoImage,
+    /// The render options are out of range
+    InvalidOptions(String),
+    Io(String),
+    Encode(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Png,
+    Svg,
+}
+
+/// Clockwise rotation of the rendered image
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rotation {
+    None,
+    Cw90,
+    Cw180,
+    Cw270,
+}
+
+/// The layers which are drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Layers {
+    /// Floor which does not belong to a segment, and scanned areas
+    pub floor: bool,
+    /// Walls and obstacles
+    pub walls: bool,
+    /// Floor which belongs to a segment, colored by segment
+    pub segments: bool,
+    /// The path driven during the current (or last) cleaning
+    pub path: bool,
+    /// Position and heading of the robot
+    pub robot: bool,
+    pub charger: bool,
+    /// No-go areas, no-mop areas and virtual walls
+    pub restrictions: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RenderOptions {
+    /// Output pixels for each map pixel, at most `MAX_SCALE`
+    pub scale: u32,
+    pub rotation: Rotation,
+    /// Crop the image to the explored area of the map
+    pub crop: bool,
+    pub layers: Layers,
+}
+
+impl Layers {
+    pub const ALL: Layers = Layers {
+        floor: true, walls: true, segments: true, path: true, robot: true, charger: true, restrictions: true
+    };
+    pub const NONE: Layers = Layers {
+        floor: false, walls: false, segments: false, path: false, robot: false, charger: false, restrictions: false
+    };
+}
+
+impl RenderOptions {
+    /// Check that the options are within range
+    pub fn validate(&self) -> Result<(), String> {
+        if self.scale > MAX_SCALE {
+            return Err(format!("scale {} is larger than {}", self.scale, MAX_SCALE));
+        }
+        Ok(())
+    }
+}
+
+impl Default for RenderOptions {
+    fn default() -> RenderOptions {
+        RenderOptions { scale: 4, rotation: Rotation::None, crop: false, layers: Layers::ALL }
+    }
+}
+
+/// The part of the map image which is rendered, in map image pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Frame {
+    column: u32,
+    row: u32,
+    width: u32,
+    height: u32,
+}
+
+impl Frame {
+    fn new(image: &MapImage, crop: bool) -> Frame {
+        let full = Frame { column: 0, row: 0, width: image.width, height: image.height };
+        if !crop {
+            return full;
+        }
+        let (mut min_column, mut min_row, mut max_column, mut max_row) = (u32::MAX, u32::MAX, 0, 0);
+        for row in 0..image.height {
+            for column in 0..image.width {
+                if image.pixel(column, row) != Some(Pixel::Outside) {
+                    min_column = min_column.min(column);
+                    min_row = min_row.min(row);
+                    max_column = max_column.max(column);
+                    max_row = max_row.max(row);
+                }
+            }
+        }
+        if min_column > max_column {
+            // nothing was explored
+            return full;
+        }
+        Frame { column: min_column, row: min_row, width: max_column - min_column + 1, height: max_row - min_row + 1 }
+    }
+
+    /// Convert robot coordinates to canvas coordinates, in map pixels, with `y` pointing down
+    fn to_canvas(&self, image: &MapImage, p: Point) -> (f64, f64) {
+        let x = p.x as f64 / PIXEL_SIZE as f64 - (image.left + self.column) as f64;
+        let y = p.y as f64 / PIXEL_SIZE as f64 - (image.top + self.row) as f64;
+        (x, self.height as f64 - y)
+    }
+}
+
+/// Render a map to an image file
+///
+/// # Arguments
+///
+/// `rrmap` - the map
+/// `options` - scale, rotation, cropping and layers
+/// `format` - format of the image
+/// `path` - the image file. It is overwritten if it exists.
+///
+pub fn render_file(rrmap: &RRMap, options: &RenderOptions, format: ImageFormat, path: &Path) -> Result<(), Error> {
+    let file = match File::create(path) {
+        Ok(file) => file,
+        Err(e) => { return Err(Io(e.to_string())); }
+    };
+    let mut writer = BufWriter::new(file);
+    match format {
+        ImageFormat::Png => render_png(rrmap, options, &mut writer)?,
+        ImageFormat::Svg => {
+            let svg = render_svg(rrmap, options)?;
+            if let Err(e) = writer.write_all(svg.as_bytes()) { return Err(Io(e.to_string())); }
+        }
+    }
+    if let Err(e) = writer.flush() { return Err(Io(e.to_string())); }
+    Ok(())
+}
+
+/// Render a map to a PNG image
+///
+/// # Arguments
+///
+/// `rrmap` - the map
+/// `options` - scale, rotation, cropping and layers
+/// `writer` - where the PNG image is written to
+///
+pub fn render_png<W: Write>(rrmap: &RRMap, options: &RenderOptions, writer: W) -> Result<(), Error> {
+    let image = match &rrmap.image {
+        Some(image) => image,
+        None => { return Err(NoImage); }
+    };
+    if let Err(e) = options.validate() {
+        return Err(InvalidOptions(e));
+    }
+    let frame = Frame::new(image, options.crop);
+    let scale = options.scale.max(1);
+    let mut canvas = match (frame.width.checked_mul(scale), frame.height.checked_mul(scale)) {
+        (Some(width), Some(height)) => Canvas::new(width, height)?,
+        _ => { return Err(InvalidOptions(format!("image of {}x{} pixels is too large to scale by {}",
+                                                 frame.width, frame.height, scale))); }
+    };
+    let s = scale as f64;
+
+    for row in 0..frame.height {
+        for column in 0..frame.width {
+            if let Some(color) = pixel_color(image.pixel(frame.column + column, frame.row + row), &options.layers) {
+                let y = frame.height - 1 - row;
+                canvas.fill_rect(column * scale, y * scale, scale, scale, color);
+            }
+        }
+    }
+
+    let to_canvas = |p: Point| { let (x, y) = frame.to_canvas(image, p); (x * s, y * s) };
+
+    if options.layers.restrictions {
+        for (areas, fill, border) in [(&rrmap.no_go_areas, COLOR_NO_GO, COLOR_NO_GO_BORDER),
+                                      (&rrmap.no_mop_areas, COLOR_NO_MOP, COLOR_NO_MOP_BORDER)].iter() {
+            for area in areas.iter() {
+                let corners: Vec<(f64, f64)> = area.iter().map(|p| to_canvas(*p)).collect();
+                canvas.fill_polygon(&corners, *fill);
+                canvas.polyline(&corners, true, LINE_WIDTH * s, *border);
+            }
+        }
+        for wall in &rrmap.virtual_walls {
+            canvas.polyline(&[to_canvas(wall.start), to_canvas(wall.end)], false, LINE_WIDTH * s, COLOR_VIRTUAL_WALL);
+        }
+    }
+    if options.layers.path {
+        if let Some(path) = &rrmap.path {
+            let points: Vec<(f64, f64)> = path.points.iter().map(|p| to_canvas(*p)).collect();
+            canvas.polyline(&points, false, LINE_WIDTH * s, COLOR_PATH);
+        }
+    }
+    if options.layers.charger {
+        if let Some(charger) = rrmap.charger {
+            let (x, y) = to_canvas(charger);
+            canvas.fill_circle(x, y, MARKER_RADIUS * s, COLOR_CHARGER);
+        }
+    }
+    if options.layers.robot {
+        if let Some(robot) = rrmap.robot_position {
+            let (x, y) = to_canvas(robot);
+            canvas.fill_circle(x, y, MARKER_RADIUS * s, COLOR_ROBOT);
+            let (dx, dy) = heading(rrmap.robot_angle.unwrap_or(0));
+            let r = MARKER_RADIUS * s;
+            canvas.polyline(&[(x, y), (x + dx * r * 1.5, y + dy * r * 1.5)], false, LINE_WIDTH * s, COLOR_WALL);
+        }
+    }
+
+    let canvas = canvas.rotate(options.rotation);
+
+    let mut encoder = png::Encoder::new(writer, canvas.width, canvas.height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut png_writer = match encoder.write_header() {
+        Ok(png_writer) => png_writer,
+        Err(e) => { return Err(Encode(e.to_string())); }
+    };
+    if let Err(e) = png_writer.write_image_data(&canvas.data) {
+        return Err(Encode(e.to_string()));
+    }
+    Ok(())
+}
+
+/// Render a map to an SVG image
+///
+/// # Arguments
+///
+/// `rrmap` - the map
+/// `options` - scale, rotation, cropping and layers
+///
+pub fn render_svg(rrmap: &RRMap, options: &RenderOptions) -> Result<String, Error> {
+    let image = match &rrmap.image {
+        Some(image) => image,
+        None => { return Err(NoImage); }
+    };
+    if let Err(e) = options.validate() {
+        return Err(InvalidOptions(e));
+    }
+    let frame = Frame::new(image, options.crop);
+    let scale = options.scale.max(1) as u64;
+    let (width, height) = match options.rotation {
+        Rotation::None | Rotation::Cw180 => (frame.width, frame.height),
+        Rotation::Cw90 | Rotation::Cw270 => (frame.height, frame.width),
+    };
+    let transform = match options.rotation {
+        Rotation::None => String::new(),
+        Rotation::Cw90 => format!(" transform=\"translate({} 0) rotate(90)\"", frame.height),
+        Rotation::Cw180 => format!(" transform=\"translate({} {}) rotate(180)\"", frame.width, frame.height),
+        Rotation::Cw270 => format!(" transform=\"translate(0 {}) rotate(270)\"", frame.width),
+    };
+
+    let mut svg = String::new();
+    svg.push_str(&format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" \
+                           viewBox=\"0 0 {} {}\" shape-rendering=\"crispEdges\">\n",
+                          width as u64 * scale, height as u64 * scale, width, height));
+    svg.push_str(&format!("<g{}>\n", transform));
+
+    // consecutive pixels of the same color are merged into a single rectangle
+    for row in 0..frame.height {
+        let y = frame.height - 1 - row;
+        let mut column = 0;
+        while column < frame.width {
+            let color = pixel_color(image.pixel(frame.column + column, frame.row + row), &options.layers);
+            let mut run = 1;
+            while column + run < frame.width &&
+                pixel_color(image.pixel(frame.column + column + run, frame.row + row), &options.layers) == color {
+                run += 1;
+            }
+            if let Some(color) = color {
+                svg.push_str(&format!("<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"1\" fill=\"{}\"/>\n",
+                                      column, y, run, svg_color(color)));
+            }
+            column += run;
+        }
+    }
+
+    let to_canvas = |p: Point| frame.to_canvas(image, p);
+
+    if options.layers.restrictions {
+        for (areas, fill, border) in [(&rrmap.no_go_areas, COLOR_NO_GO, COLOR_NO_GO_BORDER),
+                                      (&rrmap.no_mop_areas, COLOR_NO_MOP, COLOR_NO_MOP_BORDER)].iter() {
+            for area in areas.iter() {
+                let corners: Vec<(f64, f64)> = area.iter().map(|p| to_canvas(*p)).collect();
+                svg.push_str(&format!("<polygon points=\"{}\" fill=\"{}\" fill-opacity=\"{:.2}\" stroke=\"{}\" \
+                                       stroke-width=\"{}\"/>\n", svg_points(&corners), svg_color(*fill),
+                                      fill[3] as f64 / 255.0, svg_color(*border), LINE_WIDTH));
+            }
+        }
+        for wall in &rrmap.virtual_walls {
+            let (x1, y1) = to_canvas(wall.start);
+            let (x2, y2) = to_canvas(wall.end);
+            svg.push_str(&format!("<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"{}\" stroke-width=\"{}\"/>\n",
+                                  x1, y1, x2, y2, svg_color(COLOR_VIRTUAL_WALL), LINE_WIDTH));
+        }
+    }
+    if options.layers.path {
+        if let Some(path) = &rrmap.path {
+            let points: Vec<(f64, f64)> = path.points.iter().map(|p| to_canvas(*p)).collect();
+            svg.push_str(&format!("<polyline points=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"{}\" \
+                                   shape-rendering=\"auto\"/>\n", svg_points(&points), svg_color(COLOR_PATH),
+                                  LINE_WIDTH));
+        }
+    }
+    if options.layers.charger {
+        if let Some(charger) = rrmap.charger {
+            let (x, y) = to_canvas(charger);
+            svg.push_str(&format!("<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"{}\" shape-rendering=\"auto\"/>\n",
+                                  x, y, MARKER_RADIUS, svg_color(COLOR_CHARGER)));
+        }
+    }
+    if options.layers.robot {
+        if let Some(robot) = rrmap.robot_position {
+            let (x, y) = to_canvas(robot);
+            let (dx, dy) = heading(rrmap.robot_angle.unwrap_or(0));
+            svg.push_str(&format!("<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"{}\" shape-rendering=\"auto\"/>\n",
+                                  x, y, MARKER_RADIUS, svg_color(COLOR_ROBOT)));
+            svg.push_str(&format!("<line x1=\"{}\" y1=\"{}\" x2=\"{:.2}\" y2=\"{:.2}\" stroke=\"{}\" \
+                                   stroke-width=\"{}\"/>\n", x, y, x + dx * MARKER_RADIUS * 1.5,
+                                  y + dy * MARKER_RADIUS * 1.5, svg_color(COLOR_WALL), LINE_WIDTH));
+        }
+    }
+
+    svg.push_str("</g>\n</svg>\n");
+    Ok(svg)
+}
+
+/// The color of a map pixel, or `None` if it is not drawn
+fn pixel_color(pixel: Option<Pixel>, layers: &Layers) -> Option<Rgba> {
+    match pixel {
+        Some(Pixel::Floor) | Some(Pixel::Scanned) if !layers.floor => None,
+        Some(Pixel::Floor) => Some(COLOR_FLOOR),
+        Some(Pixel::Scanned) => Some(COLOR_SCANNED),
+        Some(Pixel::Wall) | Some(Pixel::Obstacle) if !layers.walls => None,
+        Some(Pixel::Wall) => Some(COLOR_WALL),
+        Some(Pixel::Obstacle) => Some(COLOR_OBSTACLE),
+        // without the segments layer, segments are drawn as plain floor
+        Some(Pixel::Segment(_id)) if !layers.segments => if layers.floor { Some(COLOR_FLOOR) } else { None },
+        Some(Pixel::Segment(id)) => Some(SEGMENT_COLORS[id as usize % SEGMENT_COLORS.len()]),
+        Some(Pixel::Outside) | Some(Pixel::Unknown(_)) | None => None,
+    }
+}
+
+/// Unit vector of a robot heading, in canvas coordinates (`y` pointing down)
+fn heading(angle: i32) -> (f64, f64) {
+    let rad = (angle as f64).to_radians();
+    (rad.cos(), -rad.sin())
+}
+
+fn svg_color(color: Rgba) -> String {
+    format!("#{:02x}{:02x}{:02x}", color[0], color[1], color[2])
+}
+
+fn svg_points(points: &[(f64, f64)]) -> String {
+    points.iter().map(|(x, y)| format!("{},{}", x, y)).collect::<Vec<String>>().join(" ")
+}
+
+/// An RGBA raster
+struct Canvas {
+    width: u32,
+    height: u32,
+    data: Vec<u8>,
+}
+
+impl Canvas {
+    /// Create a transparent canvas, or fail if it would not fit in memory
+    fn new(width: u32, height: u32) -> Result<Canvas, Error> {
+        match (width as usize).checked_mul(height as usize).and_then(|n| n.checked_mul(4)) {
+            Some(len) => Ok(Canvas { width, height, data: vec![0; len] }),
+            None => Err(InvalidOptions(format!("image of {}x{} pixels is too large", width, height)))
+        }
+    }
+
+    /// Composite a color over a pixel. Pixels outside of the canvas are ignored.
+    fn blend(&mut self, x: i64, y: i64, color: Rgba) {
+        if x < 0 || y < 0 || x >= self.width as i64 || y >= self.height as i64 {
+            return;
+        }
+        let i = (y as usize * self.width as usize + x as usize) * 4;
+        let src_alpha = color[3] as u32;
+        let dst_alpha = self.data[i + 3] as u32;
+        // both alphas are scaled by 255, so that integer arithmetic is exact enough
+        let alpha = src_alpha * 255 + dst_alpha * (255 - src_alpha);
+        if alpha == 0 {
+            return;
+        }
+        for c in 0..3 {
+            self.data[i + c] = ((color[c] as u32 * src_alpha * 255 +
+                                 self.data[i + c] as u32 * dst_alpha * (255 - src_alpha)) / alpha) as u8;
+        }
+        self.data[i + 3] = (alpha / 255) as u8;
+    }
+
+    fn fill_rect(&mut self, x: u32, y: u32, width: u32, height: u32, color: Rgba) {
+        for py in y..y + height {
+            for px in x..x + width {
+                self.blend(px as i64, py as i64, color);
+            }
+        }
+    }
+
+    fn fill_circle(&mut self, cx: f64, cy: f64, radius: f64, color: Rgba) {
+        let r = radius.ceil() as i64;
+        for py in cy as i64 - r..=cy as i64 + r {
+            for px in cx as i64 - r..=cx as i64 + r {
+                let (dx, dy) = (px as f64 + 0.5 - cx, py as f64 + 0.5 - cy);
+                if dx * dx + dy * dy <= radius * radius {
+                    self.blend(px, py, color);
+                }
+            }
+        }
+    }
+
+    /// Draw connected line segments, by stamping a square of the line width along each segment
+    fn polyline(&mut self, points: &[(f64, f64)], closed: bool, width: f64, color: Rgba) {
+        let half = (width / 2.0).max(0.5);
+        let mut segments: Vec<((f64, f64), (f64, f64))> = points.windows(2).map(|w| (w[0], w[1])).collect();
+        if closed && points.len() > 2 {
+            segments.push((points[points.len() - 1], points[0]));
+        }
+        let mut stamped = std::collections::HashSet::new();
+        for ((x1, y1), (x2, y2)) in segments {
+            let steps = (x2 - x1).abs().max((y2 - y1).abs()).ceil().max(1.0) as i64;
+            for i in 0..=steps {
+                let t = i as f64 / steps as f64;
+                let (x, y) = (x1 + (x2 - x1) * t, y1 + (y2 - y1) * t);
+                for py in (y - half).floor() as i64..(y + half).ceil() as i64 {
+                    for px in (x - half).floor() as i64..(x + half).ceil() as i64 {
+                        // translucent colors must not be blended twice into the same pixel
+                        if stamped.insert((px, py)) {
+                            self.blend(px, py, color);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Fill a polygon, using the even-odd rule on pixel centers
+    fn fill_polygon(&mut self, corners: &[(f64, f64)], color: Rgba) {
+        if corners.len() < 3 {
+            return;
+        }
+        let min_y = corners.iter().map(|c| c.1).fold(f64::MAX, f64::min).floor() as i64;
+        let max_y = corners.iter().map(|c| c.1).fold(f64::MIN, f64::max).ceil() as i64;
+        let min_x = corners.iter().map(|c| c.0).fold(f64::MAX, f64::min).floor() as i64;
+        let max_x = corners.iter().map(|c| c.0).fold(f64::MIN, f64::max).ceil() as i64;
+        for py in min_y..max_y {
+            for px in min_x..max_x {
+                let (x, y) = (px as f64 + 0.5, py as f64 + 0.5);
+                let mut inside = false;
+                let mut j = corners.len() - 1;
+                for i in 0..corners.len() {
+                    let ((xi, yi), (xj, yj)) = (corners[i], corners[j]);
+                    if (yi > y) != (yj > y) && x < (xj - xi) * (y - yi) / (yj - yi) + xi {
+                        inside = !inside;
+                    }
+                    j = i;
+                }
+                if inside {
+                    self.blend(px, py, color);
+                }
+            }
+        }
+    }
+
+    fn rotate(self, rotation: Rotation) -> Canvas {
+        if rotation == Rotation::None {
+            return self;
+        }
+        let (width, height) = match rotation {
+            Rotation::Cw90 | Rotation::Cw270 => (self.height, self.width),
+            _ => (self.width, self.height),
+        };
+        let mut rotated = Canvas { width, height, data: vec![0; self.data.len()] };
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let (rx, ry) = match rotation {
+                    Rotation::Cw90 => (self.height - 1 - y, x),
+                    Rotation::Cw180 => (self.width - 1 - x, self.height - 1 - y),
+                    _ => (y, self.width - 1 - x),
+                };
+                let src = (y as usize * self.width as usize + x as usize) * 4;
+                let dst = (ry as usize * width as usize + rx as usize) * 4;
+                rotated.data[dst..dst + 4].copy_from_slice(&self.data[src..src + 4]);
+            }
+        }
+        rotated
+    }
+}
+
+impl ImageFormat {
+    /// Guess the image format from the extension of a file name
+    pub fn from_path(path: &Path) -> Option<ImageFormat> {
+        path.extension().and_then(|e| e.to_str()).and_then(|e| ImageFormat::from_str(e).ok())
+    }
+}
+
+impl FromStr for ImageFormat {
+    type Err = String;
+
+    /// Parse an image format name: `png` or `svg`
+    fn from_str(s: &str) -> Result<ImageFormat, String> {
+        match s.to_lowercase().as_str() {
+            "png" => Ok(ImageFormat::Png),
+            "svg" => Ok(ImageFormat::Svg),
+            _ => Err(s.to_string())
+        }
+    }
+}
+
+impl FromStr for Rotation {
+    type Err = String;
+
+    /// Parse a clockwise rotation in degrees: `0`, `90`, `180` or `270`
+    fn from_str(s: &str) -> Result<Rotation, String> {
+        match s {
+            "0" => Ok(Rotation::None),
+            "90" => Ok(Rotation::Cw90),
+            "180" => Ok(Rotation::Cw180),
+            "270" => Ok(Rotation::Cw270),
+            _ => Err(s.to_string())
+        }
+    }
+}
+
+impl FromStr for Layers {
+    type Err = String;
+
+    /// Parse a comma separated list of layer names: `floor`, `walls`, `segments`, `path`, `robot`, `charger` and
+    /// `restrictions`, or `all`
+    fn from_str(s: &str) -> Result<Layers, String> {
+        let mut layers = Layers::NONE;
+        for name in s.split(',') {
+            match name.trim().to_lowercase().as_str() {
+                "all" => { layers = Layers::ALL; }
+                "floor" => { layers.floor = true; }
+                "walls" => { layers.walls = true; }
+                "segments" => { layers.segments = true; }
+                "path" => { layers.path = true; }
+                "robot" => { layers.robot = true; }
+                "charger" => { layers.charger = true; }
+                "restrictions" => { layers.restrictions = true; }
+                _ => { return Err(s.to_string()); }
+            }
+        }
+        Ok(layers)
+    }
+}
+
+impl StdError for Error {
+    fn description(&self) -> &str {
+        match &*self {
+            Error::NoImage => "Map without image",
+            Error::InvalidOptions(_e) => "Invalid render options",
+            Error::Io(_e) => "I/O error",
+            Error::Encode(_e) => "Encoding error",
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &*self {
+            Error::NoImage => f.write_str("The map has no image"),
+            Error::InvalidOptions(e) => f.write_fmt(format_args!("Invalid render options: {}", e)),
+            Error::Io(e) => f.write_fmt(format_args!("I/O error: {}", e)),
+            Error::Encode(e) => f.write_fmt(format_args!("Could not encode image: {}", e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::{self, MapPath, VirtualWall};
+
+    const FIXTURE: &[u8] = include_bytes!("../tests/fixtures/map.rrmap.gz");
+
+    /// A 10x10 map with an explored 4x3 area, a path, the robot and a no-go area
+    fn test_map() -> RRMap {
+        let mut pixels = vec![0u8; 100];
+        for row in 2..5 {
+            for column in 3..7 {
+                pixels[row * 10 + column] = if column == 3 { 0x01 } else { (16 << 3) | 0x07 };
+            }
+        }
+        let p = |x, y| Point { x, y };
+        RRMap {
+            image: Some(MapImage { top: 500, left: 500, width: 10, height: 10, segment_count: 1, pixels }),
+            robot_position: Some(p(25325, 25225)),
+            robot_angle: Some(90),
+            path: Some(MapPath { angle: 0, points: vec!(p(25200, 25125), p(25325, 25125)) }),
+            no_go_areas: vec!([p(25250, 25100), p(25300, 25100), p(25300, 25200), p(25250, 25200)]),
+            virtual_walls: vec!(VirtualWall { start: p(25100, 25100), end: p(25100, 25250) }),
+            ..Default::default()
+        }
+    }
+
+    fn decode_png(data: &[u8]) -> (u32, u32, Vec<u8>) {
+        let decoder = png::Decoder::new(data);
+        let mut reader = decoder.read_info().unwrap();
+        let mut buf = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buf).unwrap();
+        (info.width, info.height, buf)
+    }
+
+    fn png_pixel(image: &(u32, u32, Vec<u8>), x: u32, y: u32) -> Rgba {
+        let i = ((y * image.0 + x) * 4) as usize;
+        [image.2[i], image.2[i + 1], image.2[i + 2], image.2[i + 3]]
+    }
+
+    #[test]
+    fn test_render_png() {
+        let rrmap = test_map();
+        let mut out = Vec::new();
+        render_png(&rrmap, &RenderOptions { scale: 2, ..Default::default() }, &mut out).unwrap();
+        let image = decode_png(&out);
+        assert_eq!((image.0, image.1), (20, 20));
+        // row 2 of the map is row 7 from the top, and column 3 is a wall
+        assert_eq!(png_pixel(&image, 6, 14), COLOR_WALL);
+        assert_eq!(png_pixel(&image, 0, 0), [0, 0, 0, 0]);
+
+        let mut options = RenderOptions { scale: 1, crop: true, ..Default::default() };
+        options.layers = Layers::from_str("segments").unwrap();
+        out.clear();
+        render_png(&rrmap, &options, &mut out).unwrap();
+        let image = decode_png(&out);
+        assert_eq!((image.0, image.1), (4, 3));
+        assert_eq!(png_pixel(&image, 0, 0), [0, 0, 0, 0]);
+        assert_eq!(png_pixel(&image, 1, 0), SEGMENT_COLORS[0]);
+
+        options.rotation = Rotation::Cw90;
+        out.clear();
+        render_png(&rrmap, &options, &mut out).unwrap();
+        let image = decode_png(&out);
+        assert_eq!((image.0, image.1), (3, 4));
+        // the wall column is now the top row
+        assert_eq!(png_pixel(&image, 1, 0), [0, 0, 0, 0]);
+        assert_eq!(png_pixel(&image, 1, 1), SEGMENT_COLORS[0]);
+    }
+
+    #[test]
+    fn test_render_svg() {
+        let rrmap = test_map();
+        let svg = render_svg(&rrmap, &RenderOptions { crop: true, ..Default::default() }).unwrap();
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"16\" height=\"12\" \
+                                 viewBox=\"0 0 4 3\""));
+        // each row has a wall pixel, and a run of three segment pixels
+        assert_eq!(svg.matches("<rect ").count(), 6);
+        assert!(svg.contains("<rect x=\"1\" y=\"0\" width=\"3\" height=\"1\" fill=\"#f1c40f\"/>"));
+        assert!(svg.contains("<polyline points=\"1,2.5 3.5,2.5\""));
+        assert!(svg.contains("<polygon points=\"2,3 3,3 3,1 2,1\""));
+        assert!(svg.contains("<circle cx=\"3.5\" cy=\"0.5\""));
+        assert_eq!(svg.matches("<line ").count(), 2);
+
+        let options = RenderOptions { rotation: Rotation::Cw270, layers: Layers::from_str("walls").unwrap(),
+                                      ..Default::default() };
+        let svg = render_svg(&rrmap, &options).unwrap();
+        assert!(svg.contains("<g transform=\"translate(0 10) rotate(270)\">"));
+        assert_eq!(svg.matches("<rect ").count(), 3);
+        assert!(!svg.contains("<circle"));
+    }
+
+    #[test]
+    fn test_render_limits() {
+        let rrmap = test_map();
+        let options = RenderOptions { scale: MAX_SCALE + 1, ..Default::default() };
+        assert!(render_png(&rrmap, &options, &mut Vec::new()).is_err());
+        assert!(render_svg(&rrmap, &RenderOptions { scale: u32::MAX, ..Default::default() }).is_err());
+
+        // translucent colors keep their color over the transparent background
+        let mut canvas = Canvas::new(1, 1).unwrap();
+        canvas.blend(0, 0, COLOR_NO_GO);
+        assert_eq!(canvas.data, COLOR_NO_GO);
+        canvas.fill_rect(0, 0, 1, 1, COLOR_FLOOR);
+        canvas.blend(0, 0, [0xff, 0xff, 0xff, 0x80]);
+        assert_eq!(canvas.data, [0xaa, 0xd7, 0xfd, 0xff]);
+    }
+
+    #[test]
+    fn test_render_fixture() {
+        let rrmap = map::parse(FIXTURE).unwrap();
+        let mut out = Vec::new();
+        render_png(&rrmap, &RenderOptions::default(), &mut out).unwrap();
+        assert_eq!((decode_png(&out).0, decode_png(&out).1), (32, 24));
+        assert!(render_svg(&rrmap, &RenderOptions::default()).is_ok());
+
+        assert!(render_svg(&RRMap::default(), &RenderOptions::default()).is_err());
+        assert!(Layers::from_str("floor,unknown").is_err());
+        assert_eq!(Rotation::from_str("180"), Ok(Rotation::Cw180));
+        assert!(Rotation::from_str("45").is_err());
+        assert_eq!(ImageFormat::from_path(Path::new("/tmp/map.SVG")), Some(ImageFormat::Svg));
+        assert_eq!(ImageFormat::from_path(Path::new("/tmp/map")), None);
+    }
+}
@@ -0,0 +1,250 @@
+//! No-go zones, no-mop zones and virtual walls, which are saved on the robot with the `save_map` method.
+//!
+//! The restrictions are kept in a JSON or YAML file (picked by the file extension), so that they can be version
+//! controlled, and restored after the map was reset, e.g.
+//!
+//! ```yaml
+//! no_go_zones:
+//!   - {x1: 24600, y1: 24900, x2: 25100, y2: 25200}
+//! no_mop_zones:
+//!   - {x1: 25200, y1: 25100, x2: 25500, y2: 25300}
+//! virtual_walls:
+//!   - {x1: 24500, y1: 25000, x2: 24500, y2: 25400}
+//! ```
+//!
+//! All coordinates are in the robot's coordinate space (see `zone`). No-mop zones are only supported by robots
+//! which can mop.
+
+use crate::map::{RRMap, Area};
+use crate::zone::MAX_COORDINATE;
+use serde::{Serialize, Deserialize};
+use serde_json::{Value, json};
+use std::fs;
+use std::path::Path;
+
+/// The type of a no-go zone, in the parameters of the `save_map` method
+const TYPE_NO_GO_ZONE: u32 = 0;
+/// The type of a virtual wall, in the parameters of the `save_map` method
+const TYPE_VIRTUAL_WALL: u32 = 1;
+/// The type of a no-mop zone, in the parameters of the `save_map` method
+const TYPE_NO_MOP_ZONE: u32 = 2;
+
+/// A rectangle, given by two opposite corners
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rect {
+    pub x1: u32,
+    pub y1: u32,
+    pub x2: u32,
+    pub y2: u32,
+}
+
+/// A virtual wall, from (`x1`, `y1`) to (`x2`, `y2`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Wall {
+    pub x1: u32,
+    pub y1: u32,
+    pub x2: u32,
+    pub y2: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Restrictions {
+    #[serde(default)]
+    pub no_go_zones: Vec<Rect>,
+    #[serde(default)]
+    pub no_mop_zones: Vec<Rect>,
+    #[serde(default)]
+    pub virtual_walls: Vec<Wall>,
+}
+
+impl Rect {
+    /// Create a rectangle from two opposite corners, in any order
+    pub fn new(xa: u32, ya: u32, xb: u32, yb: u32) -> Rect {
+        Rect { x1: xa.min(xb), y1: ya.min(yb), x2: xa.max(xb), y2: ya.max(yb) }
+    }
+
+    /// The bounding rectangle of an area reported in a map
+    pub fn bounding(area: &Area) -> Rect {
+        let x1 = area.iter().map(|p| p.x).min().unwrap_or(0);
+        let y1 = area.iter().map(|p| p.y).min().unwrap_or(0);
+        let x2 = area.iter().map(|p| p.x).max().unwrap_or(0);
+        let y2 = area.iter().map(|p| p.y).max().unwrap_or(0);
+        Rect { x1, y1, x2, y2 }
+    }
+
+    /// Check that the rectangle is not empty, and is inside the robot's coordinate space
+    pub fn validate(&self) -> Result<(), String> {
+        if self.x1 >= self.x2 || self.y1 >= self.y2 {
+            return Err(format!("zone {:?} is empty, or its corners are not ordered", self));
+        }
+        if self.x2 > MAX_COORDINATE || self.y2 > MAX_COORDINATE {
+            return Err(format!("zone {:?} is outside of the map (0..{})", self, MAX_COORDINATE));
+        }
+        Ok(())
+    }
+
+    /// The four corners of the rectangle, counter-clockwise from the lower left corner
+    fn corners(&self) -> [u32; 8] {
+        [self.x1, self.y1, self.x2, self.y1, self.x2, self.y2, self.x1, self.y2]
+    }
+}
+
+impl Wall {
+    /// Check that the wall is not a single point, and is inside the robot's coordinate space
+    pub fn validate(&self) -> Result<(), String> {
+        if self.x1 == self.x2 && self.y1 == self.y2 {
+            return Err(format!("virtual wall {:?} has no length", self));
+        }
+        if self.x1.max(self.x2) > MAX_COORDINATE || self.y1.max(self.y2) > MAX_COORDINATE {
+            return Err(format!("virtual wall {:?} is outside of the map (0..{})", self, MAX_COORDINATE));
+        }
+        Ok(())
+    }
+}
+
+impl Restrictions {
+    /// The restrictions which are present in a map
+    pub fn from_map(rrmap: &RRMap) -> Restrictions {
+        Restrictions {
+            no_go_zones: rrmap.no_go_areas.iter().map(Rect::bounding).collect(),
+            no_mop_zones: rrmap.no_mop_areas.iter().map(Rect::bounding).collect(),
+            virtual_walls: rrmap.virtual_walls.iter()
+                .map(|w| Wall { x1: w.start.x, y1: w.start.y, x2: w.end.x, y2: w.end.y })
+                .collect(),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        for zone in self.no_go_zones.iter().chain(self.no_mop_zones.iter()) {
+            zone.validate()?;
+        }
+        for wall in &self.virtual_walls {
+            wall.validate()?;
+        }
+        Ok(())
+    }
+
+    /// The restrictions, as used in the parameters of the `save_map` method. All the restrictions which are not
+    /// given are removed from the robot.
+    pub fn to_params(&self) -> Value {
+        let mut params = Vec::new();
+        for zone in &self.no_go_zones {
+            let c = zone.corners();
+            params.push(json!([TYPE_NO_GO_ZONE, c[0], c[1], c[2], c[3], c[4], c[5], c[6], c[7]]));
+        }
+        for wall in &self.virtual_walls {
+            params.push(json!([TYPE_VIRTUAL_WALL, wall.x1, wall.y1, wall.x2, wall.y2]));
+        }
+        for zone in &self.no_mop_zones {
+            let c = zone.corners();
+            params.push(json!([TYPE_NO_MOP_ZONE, c[0], c[1], c[2], c[3], c[4], c[5], c[6], c[7]]));
+        }
+        Value::Array(params)
+    }
+
+    /// Load restrictions from a JSON file, or from a YAML file (with a `.yaml` or `.yml` extension)
+    ///
+    /// # Arguments
+    ///
+    /// `path` - the restrictions file
+    ///
+    pub fn load(path: &Path) -> Result<Restrictions, String> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) => { return Err(format!("{}: {}", path.display(), e)); }
+        };
+        let restrictions = if is_yaml(path) {
+            serde_yaml::from_str(&content).map_err(|e| e.to_string())
+        } else {
+            serde_json::from_str(&content).map_err(|e| e.to_string())
+        };
+        restrictions.map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// Save restrictions to a JSON file, or to a YAML file (with a `.yaml` or `.yml` extension)
+    ///
+    /// # Arguments
+    ///
+    /// `path` - the restrictions file. It is overwritten if it exists.
+    ///
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let content = if is_yaml(path) {
+            serde_yaml::to_string(self).map_err(|e| e.to_string())?
+        } else {
+            serde_json::to_string_pretty(self).map_err(|e| e.to_string())? + "\n"
+        };
+        fs::write(path, content).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+}
+
+fn is_yaml(path: &Path) -> bool {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some(ext) => ext.eq_ignore_ascii_case("yaml") || ext.eq_ignore_ascii_case("yml"),
+        None => false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map;
+    use crate::testutil::TempPath;
+
+    const FIXTURE: &[u8] = include_bytes!("../tests/fixtures/map.rrmap.gz");
+
+    fn test_restrictions() -> Restrictions {
+        Restrictions {
+            no_go_zones: vec!(Rect::new(25100, 25200, 24600, 24900)),
+            no_mop_zones: vec!(Rect::new(25200, 25100, 25500, 25300)),
+            virtual_walls: vec!(Wall { x1: 24500, y1: 25000, x2: 24500, y2: 25400 },
+                                Wall { x1: 25200, y1: 24800, x2: 25600, y2: 24800 }),
+        }
+    }
+
+    #[test]
+    fn test_restrictions_params() {
+        let restrictions = test_restrictions();
+        assert!(restrictions.validate().is_ok());
+        assert_eq!(restrictions.to_params(), json!([
+            [0, 24600, 24900, 25100, 24900, 25100, 25200, 24600, 25200],
+            [1, 24500, 25000, 24500, 25400],
+            [1, 25200, 24800, 25600, 24800],
+            [2, 25200, 25100, 25500, 25100, 25500, 25300, 25200, 25300],
+        ]));
+        assert_eq!(Restrictions::default().to_params(), json!([]));
+    }
+
+    #[test]
+    fn test_restrictions_validate() {
+        assert!(Rect { x1: 25100, y1: 24900, x2: 24600, y2: 25200 }.validate().is_err());
+        assert!(Rect::new(25100, 24900, 25100, 25200).validate().is_err());
+        assert!(Rect::new(25100, 24900, 52000, 25200).validate().is_err());
+        assert!(Wall { x1: 24500, y1: 25000, x2: 24500, y2: 25000 }.validate().is_err());
+        assert!(Wall { x1: 24500, y1: 25000, x2: 24500, y2: 60000 }.validate().is_err());
+
+        let mut restrictions = test_restrictions();
+        restrictions.virtual_walls.push(Wall { x1: 1, y1: 1, x2: 1, y2: 1 });
+        assert!(restrictions.validate().is_err());
+    }
+
+    #[test]
+    fn test_restrictions_from_map() {
+        let rrmap = map::parse(FIXTURE).unwrap();
+        assert_eq!(Restrictions::from_map(&rrmap), test_restrictions());
+    }
+
+    #[test]
+    fn test_restrictions_files() {
+        let restrictions = test_restrictions();
+        for name in ["restrictions.json", "restrictions.yaml"].iter() {
+            let path = TempPath::new(name);
+            restrictions.save(&path).unwrap();
+            assert_eq!(Restrictions::load(&path).unwrap(), restrictions);
+        }
+
+        let yaml = "no_go_zones:\n  - {x1: 24600, y1: 24900, x2: 25100, y2: 25200}\n";
+        let restrictions: Restrictions = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(restrictions.no_go_zones, vec!(Rect::new(24600, 24900, 25100, 25200)));
+        assert!(restrictions.virtual_walls.is_empty());
+    }
+}
@@ -0,0 +1,90 @@
+//! User-supplied names for the segments (rooms) of the map.
+//!
+//! The robot identifies rooms by segment ids (e.g. `16`, `17`), which are assigned when the map is created. Since
+//! the segment ids are different for each map, the names are kept in a JSON file which maps each name to a segment
+//! id, e.g.
+//!
+//! ```json
+//! {
+//!     "Kitchen": 16,
+//!     "Living room": 17
+//! }
+//! ```
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Room names, and the segment id of each room
+pub type RoomNames = BTreeMap<String, u32>;
+
+/// Load room names from a JSON file
+///
+/// # Arguments
+///
+/// `path` - the room names file
+///
+pub fn load_room_names(path: &Path) -> Result<RoomNames, String> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) => { return Err(format!("{}: {}", path.display(), e)); }
+    };
+    match serde_json::from_str(&content) {
+        Ok(names) => Ok(names),
+        Err(e) => Err(format!("{}: {}", path.display(), e))
+    }
+}
+
+/// Return the segment ids of the given rooms. Room names are matched case-insensitively.
+///
+/// # Arguments
+///
+/// `names` - the room names
+/// `rooms` - the names of the rooms to look up
+///
+pub fn segments_for(names: &RoomNames, rooms: &[&str]) -> Result<Vec<u32>, String> {
+    rooms.iter().map(|room| {
+        match names.iter().find(|(name, _segment)| name.to_lowercase() == room.to_lowercase()) {
+            Some((_name, segment)) => Ok(*segment),
+            None => Err(format!("unknown room {}", room))
+        }
+    }).collect()
+}
+
+/// Return the name of a segment, if it has one
+///
+/// # Arguments
+///
+/// `names` - the room names
+/// `segment` - the segment id
+///
+pub fn name_of(names: &RoomNames, segment: u32) -> Option<&str> {
+    names.iter().find(|(_name, s)| **s == segment).map(|(name, _s)| name.as_str())
+}
+
+/// Parse a room rename, as `segment_id=name`
+pub fn parse_rename(s: &str) -> Result<(u32, String), String> {
+    let mut parts = s.splitn(2, '=');
+    match (parts.next().map(|id| id.trim().parse::<u32>()), parts.next().map(|name| name.trim())) {
+        (Some(Ok(segment)), Some(name)) if !name.is_empty() => Ok((segment, name.to_string())),
+        _ => Err(s.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_room_names() {
+        let names: RoomNames = serde_json::from_str("{\"Kitchen\": 16, \"Living room\": 17}").unwrap();
+        assert_eq!(segments_for(&names, &["kitchen", "Living Room"]), Ok(vec!(16, 17)));
+        assert!(segments_for(&names, &["Attic"]).is_err());
+        assert_eq!(name_of(&names, 17), Some("Living room"));
+        assert_eq!(name_of(&names, 18), None);
+
+        assert_eq!(parse_rename("16=Living room"), Ok((16, "Living room".to_string())));
+        assert!(parse_rename("16=").is_err());
+        assert!(parse_rename("Kitchen").is_err());
+    }
+}
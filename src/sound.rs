@@ -0,0 +1,115 @@
+//! The state of a voice pack installation, as reported in the `state` field of the `get_sound_progress` response.
+
+use std::fmt;
+
+/// The highest sound volume
+pub const MAX_VOLUME: u32 = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SoundInstallState {
+    /// No installation is in progress
+    Idle,
+    Downloading,
+    Installing,
+    Installed,
+    /// The installation failed. The reason is reported in the `error` field.
+    Failed,
+    /// A state code which is not known to this library
+    Unknown(i32),
+}
+
+impl SoundInstallState {
+    /// The numeric state code used by the device
+    pub fn code(&self) -> i32 {
+        match *self {
+            SoundInstallState::Idle => 0,
+            SoundInstallState::Downloading => 1,
+            SoundInstallState::Installing => 2,
+            SoundInstallState::Installed => 3,
+            SoundInstallState::Failed => 4,
+            SoundInstallState::Unknown(code) => code,
+        }
+    }
+
+    /// Return `true` if the installation has ended, either successfully or not
+    pub fn is_done(&self) -> bool {
+        matches!(*self, SoundInstallState::Installed | SoundInstallState::Failed)
+    }
+}
+
+/// Follows the states reported while a voice pack is installed, to tell when the installation has ended
+#[derive(Debug, Default)]
+pub struct SoundInstallTracker {
+    started: bool,
+}
+
+impl SoundInstallTracker {
+    /// Record a reported state, and return the final state if the installation has ended
+    ///
+    /// The state is briefly idle before the download starts. Once the installation has started, going back to idle
+    /// means the robot abandoned it, which is reported as `SoundInstallState::Failed`.
+    ///
+    /// # Arguments
+    ///
+    /// * `state`: the state from the latest `get_sound_progress` response
+    pub fn update(&mut self, state: SoundInstallState) -> Option<SoundInstallState> {
+        match state {
+            SoundInstallState::Idle if self.started => Some(SoundInstallState::Failed),
+            SoundInstallState::Idle => None,
+            state if state.is_done() => Some(state),
+            _ => {
+                self.started = true;
+                None
+            }
+        }
+    }
+}
+
+impl From<i32> for SoundInstallState {
+    fn from(code: i32) -> SoundInstallState {
+        match code {
+            0 => SoundInstallState::Idle,
+            1 => SoundInstallState::Downloading,
+            2 => SoundInstallState::Installing,
+            3 => SoundInstallState::Installed,
+            4 => SoundInstallState::Failed,
+            code => SoundInstallState::Unknown(code),
+        }
+    }
+}
+
+impl fmt::Display for SoundInstallState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SoundInstallState::Idle => f.write_str("Idle"),
+            SoundInstallState::Downloading => f.write_str("Downloading"),
+            SoundInstallState::Installing => f.write_str("Installing"),
+            SoundInstallState::Installed => f.write_str("Installed"),
+            SoundInstallState::Failed => f.write_str("Failed"),
+            SoundInstallState::Unknown(code) => f.write_fmt(format_args!("Unknown ({})", code)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_install_tracker() {
+        let mut tracker = SoundInstallTracker::default();
+        assert_eq!(tracker.update(SoundInstallState::Idle), None);
+        assert_eq!(tracker.update(SoundInstallState::Downloading), None);
+        assert_eq!(tracker.update(SoundInstallState::Installing), None);
+        assert_eq!(tracker.update(SoundInstallState::Installed), Some(SoundInstallState::Installed));
+
+        let mut tracker = SoundInstallTracker::default();
+        assert_eq!(tracker.update(SoundInstallState::Downloading), None);
+        assert_eq!(tracker.update(SoundInstallState::Idle), Some(SoundInstallState::Failed));
+
+        let mut tracker = SoundInstallTracker::default();
+        assert_eq!(tracker.update(SoundInstallState::Failed), Some(SoundInstallState::Failed));
+        assert!(!SoundInstallState::Unknown(9).is_done());
+        assert!(!SoundInstallState::Idle.is_done());
+    }
+}
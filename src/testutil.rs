@@ -0,0 +1,45 @@
+//! Helpers shared by the unit tests.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// A path in the temporary directory, unique to the test which created it, removed when dropped
+pub struct TempPath {
+    path: PathBuf,
+}
+
+impl TempPath {
+    /// Create a new, not yet existing, temporary path
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: the file name suffix; the extension is kept so the format can be derived from it
+    pub fn new(name: &str) -> TempPath {
+        let id = NEXT_ID.fetch_add(1, Ordering::SeqCst);
+        let path = std::env::temp_dir().join(format!("roborockutil-{}-{}-{}", std::process::id(), id, name));
+        let _ = std::fs::remove_file(&path);
+        TempPath { path }
+    }
+}
+
+impl std::ops::Deref for TempPath {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl AsRef<Path> for TempPath {
+    fn as_ref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TempPath {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
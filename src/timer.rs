@@ -0,0 +1,351 @@
+//! The cleaning timers (schedules) stored on the vacuum robot, as reported by the `get_timer` method.
+//!
+//! Each timer is reported as `[id, "on" | "off", [cron, [action, params]]]`, e.g.
+//!
+//! ```json
+//! ["1620000000000", "on", ["30 9 * * 1,3,5", ["start_clean", {"fan_power": 102, "segments": "16,17", "repeat": 1}]]]
+//! ```
+//!
+//! where the id is the creation time of the timer in milliseconds, and `cron` is a cron-style spec
+//! `minute hour day-of-month month day-of-week`. Timers created by the app are weekly (day-of-month and month are
+//! `*`), while one-off timers have a fixed day-of-month and month. Older firmware uses an empty string for `params`.
+
+use crate::fanspeed::FanSpeed;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The action of the timers which start a cleaning
+const ACTION_START_CLEAN: &str = "start_clean";
+
+/// Names of the days of the week, in cron order (`0` is Sunday)
+const WEEKDAY_NAMES: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+
+/// A set of days of the week, with bit `n` set for cron day `n` (`0` is Sunday)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Weekdays(u8);
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(try_from = "Vec<Value>")]
+pub struct CleanTimer {
+    /// Id of the timer, which is its creation time in milliseconds
+    pub id: String,
+    pub enabled: bool,
+    pub hour: u32,
+    pub minute: u32,
+    /// The days of the week on which the timer runs
+    pub weekdays: Weekdays,
+    /// The `(day, month)` of one-off timers
+    pub date: Option<(u32, u32)>,
+    /// Suction power for the cleaning, in the numeric scheme of the firmware (see `FanSpeedScheme`)
+    pub fan_power: Option<i32>,
+    /// Segments (rooms) to clean. The whole map is cleaned if not given.
+    pub segments: Option<Vec<u32>>,
+}
+
+impl Weekdays {
+    pub const ALL: Weekdays = Weekdays(0b111_1111);
+    pub const WORKDAYS: Weekdays = Weekdays(0b011_1110);
+    pub const WEEKEND: Weekdays = Weekdays(0b100_0001);
+
+    /// Return `true` if the set contains the cron day `day` (`0` is Sunday)
+    pub fn contains(&self, day: u32) -> bool {
+        day < 7 && self.0 & (1 << day) != 0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// The days of the week, as the day-of-week field of a cron spec
+    pub fn to_cron(&self) -> String {
+        if *self == Weekdays::ALL {
+            return "*".to_string();
+        }
+        (0..7).filter(|d| self.contains(*d)).map(|d| d.to_string()).collect::<Vec<String>>().join(",")
+    }
+
+    /// Parse the day-of-week field of a cron spec, e.g. `*`, `1,3,5` or `1-5`. Both `0` and `7` are Sunday.
+    pub fn from_cron(field: &str) -> Result<Weekdays, String> {
+        if field == "*" {
+            return Ok(Weekdays::ALL);
+        }
+        let mut days = 0u8;
+        for part in field.split(',') {
+            let mut bounds = part.splitn(2, '-').map(|d| d.trim().parse::<u32>());
+            let (first, last) = match (bounds.next(), bounds.next()) {
+                (Some(Ok(first)), None) => (first, first),
+                (Some(Ok(first)), Some(Ok(last))) => (first, last),
+                _ => { return Err(format!("invalid day of week {}", field)); }
+            };
+            if first > last || last > 7 {
+                return Err(format!("invalid day of week {}", field));
+            }
+            for day in first..=last {
+                days |= 1 << (day % 7);
+            }
+        }
+        Ok(Weekdays(days))
+    }
+}
+
+impl CleanTimer {
+    /// Create an enabled weekly timer, which cleans the whole map with the current suction power. The id is
+    /// derived from the current time.
+    pub fn new(hour: u32, minute: u32, weekdays: Weekdays) -> CleanTimer {
+        let millis = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis()).unwrap_or(0);
+        CleanTimer {
+            id: millis.to_string(),
+            enabled: true,
+            hour,
+            minute,
+            weekdays,
+            date: None,
+            fan_power: None,
+            segments: None,
+        }
+    }
+
+    /// The suction power preset of the timer, if it has one
+    pub fn fan_speed(&self) -> Option<FanSpeed> {
+        self.fan_power.map(FanSpeed::from)
+    }
+
+    /// Check that the timer has a valid time of day, and runs on at least one day
+    pub fn validate(&self) -> Result<(), String> {
+        if self.hour > 23 || self.minute > 59 {
+            return Err(format!("{:02}:{:02} is not a valid time of day", self.hour, self.minute));
+        }
+        if let Some((day, month)) = self.date {
+            if day < 1 || day > 31 || month < 1 || month > 12 {
+                return Err(format!("{}.{} is not a valid date", day, month));
+            }
+        } else if self.weekdays.is_empty() {
+            return Err("the timer does not run on any day".to_string());
+        }
+        if let Some(segments) = &self.segments {
+            if segments.is_empty() {
+                return Err("no segments given".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    /// The schedule of the timer, as a cron spec
+    pub fn cron(&self) -> String {
+        match self.date {
+            Some((day, month)) => format!("{} {} {} {} *", self.minute, self.hour, day, month),
+            None => format!("{} {} * * {}", self.minute, self.hour, self.weekdays.to_cron()),
+        }
+    }
+
+    /// The timer, as used in the parameters of the `set_timer` method
+    pub fn to_params(&self) -> Value {
+        let mut action_params = serde_json::Map::new();
+        if let Some(fan_power) = self.fan_power {
+            action_params.insert("fan_power".to_string(), json!(fan_power));
+        }
+        if let Some(segments) = &self.segments {
+            let segments: Vec<String> = segments.iter().map(|s| s.to_string()).collect();
+            action_params.insert("segments".to_string(), json!(segments.join(",")));
+            action_params.insert("repeat".to_string(), json!(1));
+        }
+        let action_params = if action_params.is_empty() { json!("") } else { Value::Object(action_params) };
+
+        json!([[self.id, [self.cron(), [ACTION_START_CLEAN, action_params]]]])
+    }
+}
+
+/// Parse a cron spec into `(minute, hour, date, weekdays)`
+fn parse_cron(cron: &str) -> Result<(u32, u32, Option<(u32, u32)>, Weekdays), String> {
+    let fields: Vec<&str> = cron.split_whitespace().collect();
+    if fields.len() != 5 {
+        return Err(format!("invalid cron spec {}", cron));
+    }
+    let minute = fields[0].parse::<u32>().map_err(|_e| format!("invalid cron spec {}", cron))?;
+    let hour = fields[1].parse::<u32>().map_err(|_e| format!("invalid cron spec {}", cron))?;
+    let date = match (fields[2], fields[3]) {
+        ("*", "*") => None,
+        (day, month) => match (day.parse::<u32>(), month.parse::<u32>()) {
+            (Ok(day), Ok(month)) => Some((day, month)),
+            _ => { return Err(format!("unsupported cron spec {}", cron)); }
+        }
+    };
+    let weekdays = Weekdays::from_cron(fields[4])?;
+    Ok((minute, hour, date, weekdays))
+}
+
+/// Parse the segments of a timer, which are reported as a comma separated string, or as an array
+fn parse_segments(value: &Value) -> Option<Vec<u32>> {
+    match value {
+        Value::String(s) => s.split(',').map(|s| s.trim().parse::<u32>().ok()).collect(),
+        Value::Array(a) => a.iter().map(|s| s.as_u64().map(|s| s as u32)).collect(),
+        _ => None
+    }
+}
+
+/// Parse each timer of a `get_timer` result, so that a timer which cannot be parsed does not prevent the others from
+/// being listed
+pub fn parse_timers(timers: Vec<Value>) -> Vec<Result<CleanTimer, String>> {
+    timers.into_iter().map(|t| match t {
+        Value::Array(v) => CleanTimer::try_from(v),
+        t => Err(format!("invalid timer {:?}", t))
+    }).collect()
+}
+
+impl TryFrom<Vec<Value>> for CleanTimer {
+    type Error = String;
+
+    fn try_from(v: Vec<Value>) -> Result<CleanTimer, String> {
+        let id = match v.first() {
+            Some(Value::String(id)) => id.clone(),
+            Some(Value::Number(id)) => id.to_string(),
+            _ => { return Err(format!("invalid timer {:?}", v)); }
+        };
+        let enabled = match v.get(1).and_then(|e| e.as_str()) {
+            Some("on") => true,
+            Some("off") => false,
+            _ => { return Err(format!("invalid timer {:?}", v)); }
+        };
+        let cron = match v.get(2).and_then(|s| s.as_array()).and_then(|s| s.first()).and_then(|c| c.as_str()) {
+            Some(cron) => cron,
+            None => { return Err(format!("invalid timer {:?}", v)); }
+        };
+        let (minute, hour, date, weekdays) = parse_cron(cron)?;
+
+        let action_params = v.get(2).and_then(|s| s.get(1)).and_then(|a| a.get(1));
+        let fan_power = action_params.and_then(|p| p.get("fan_power")).and_then(|f| f.as_i64()).map(|f| f as i32);
+        let segments = action_params.and_then(|p| p.get("segments")).and_then(parse_segments);
+
+        Ok(CleanTimer { id, enabled, hour, minute, weekdays, date, fan_power, segments })
+    }
+}
+
+impl fmt::Display for Weekdays {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Weekdays::ALL => f.write_str("Daily"),
+            Weekdays::WORKDAYS => f.write_str("Mon-Fri"),
+            Weekdays::WEEKEND => f.write_str("Sat,Sun"),
+            _ => {
+                // list the days starting with Monday
+                let names: Vec<&str> = (1..8).map(|d| d % 7).filter(|d| self.contains(*d))
+                    .map(|d| WEEKDAY_NAMES[d as usize]).collect();
+                f.write_str(&names.join(","))
+            }
+        }
+    }
+}
+
+impl FromStr for Weekdays {
+    type Err = String;
+
+    /// Parse `daily`, `workdays`, `weekend`, a comma separated list of day names (e.g. `mon,wed,fri`), or a cron
+    /// day-of-week field (e.g. `1-5`)
+    fn from_str(s: &str) -> Result<Weekdays, String> {
+        match s.to_lowercase().as_str() {
+            "daily" => Ok(Weekdays::ALL),
+            "workdays" | "weekdays" => Ok(Weekdays::WORKDAYS),
+            "weekend" | "weekends" => Ok(Weekdays::WEEKEND),
+            other => {
+                if let Ok(weekdays) = Weekdays::from_cron(other) {
+                    return Ok(weekdays);
+                }
+                let mut days = 0u8;
+                for name in other.split(',') {
+                    match WEEKDAY_NAMES.iter().position(|n| n.to_lowercase() == name.trim()) {
+                        Some(day) => { days |= 1 << day; }
+                        None => { return Err(s.to_string()); }
+                    }
+                }
+                Ok(Weekdays(days))
+            }
+        }
+    }
+}
+
+impl fmt::Display for CleanTimer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_fmt(format_args!("{:02}:{:02} ", self.hour, self.minute))?;
+        match self.date {
+            Some((day, month)) => f.write_fmt(format_args!("on {:02}.{:02}", day, month))?,
+            None => f.write_fmt(format_args!("{}", self.weekdays))?,
+        }
+        if let Some(fan_speed) = self.fan_speed() {
+            f.write_fmt(format_args!(", fan speed {}", fan_speed))?;
+        }
+        if let Some(segments) = &self.segments {
+            f.write_fmt(format_args!(", segments {:?}", segments))?;
+        }
+        f.write_str(if self.enabled { " (enabled)" } else { " (disabled)" })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timer_response() {
+        let timers: Vec<CleanTimer> = serde_json::from_value(json!([
+            ["1488667794112", "off", ["49 22 * * 6", ["start_clean", ""]]],
+            ["1620000000000", "on", ["30 9 * * 1-5", ["start_clean", {"fan_power": 102, "segments": "16,17",
+                                                                      "repeat": 1}]]],
+            ["1620000000001", "on", ["0 10 24 12 *", ["start_clean", -1]]]
+        ])).unwrap();
+
+        assert_eq!(timers[0].id, "1488667794112");
+        assert!(!timers[0].enabled);
+        assert_eq!((timers[0].hour, timers[0].minute), (22, 49));
+        assert_eq!(timers[0].weekdays.to_string(), "Sat");
+        assert_eq!(timers[0].fan_power, None);
+
+        assert_eq!(timers[1].weekdays, Weekdays::WORKDAYS);
+        assert_eq!(timers[1].fan_speed(), Some(FanSpeed::Balanced));
+        assert_eq!(timers[1].segments, Some(vec!(16, 17)));
+        assert_eq!(timers[1].to_string(), "09:30 Mon-Fri, fan speed Balanced, segments [16, 17] (enabled)");
+
+        assert_eq!(timers[2].date, Some((24, 12)));
+        assert_eq!(timers[2].cron(), "0 10 24 12 *");
+
+        assert!(serde_json::from_value::<Vec<CleanTimer>>(json!([["1", "on", ["bad cron", ["start_clean", ""]]]]))
+            .is_err());
+
+        let timers = parse_timers(vec!(json!(["1", "on", ["bad cron", ["start_clean", ""]]]), json!(2),
+                                       json!(["1488667794112", "off", ["49 22 * * 6", ["start_clean", ""]]])));
+        assert!(timers[0].is_err());
+        assert!(timers[1].is_err());
+        assert_eq!(timers[2].as_ref().map(|t| t.minute), Ok(49));
+    }
+
+    #[test]
+    fn test_timer_params() {
+        let mut timer = CleanTimer::new(7, 5, Weekdays::from_str("mon,wed,sun").unwrap());
+        timer.id = "1620000000000".to_string();
+        assert_eq!(timer.cron(), "5 7 * * 0,1,3");
+        assert_eq!(timer.to_params(), json!([["1620000000000", ["5 7 * * 0,1,3", ["start_clean", ""]]]]));
+
+        timer.weekdays = Weekdays::ALL;
+        timer.fan_power = Some(104);
+        timer.segments = Some(vec!(16, 18));
+        assert_eq!(timer.to_params(), json!([["1620000000000", ["5 7 * * *", ["start_clean",
+            {"fan_power": 104, "repeat": 1, "segments": "16,18"}]]]]));
+    }
+
+    #[test]
+    fn test_weekdays() {
+        assert_eq!(Weekdays::from_str("daily"), Ok(Weekdays::ALL));
+        assert_eq!(Weekdays::from_str("1-5"), Ok(Weekdays::WORKDAYS));
+        assert_eq!(Weekdays::from_str("Sat,Sun"), Ok(Weekdays::WEEKEND));
+        assert_eq!(Weekdays::from_cron("6,7"), Ok(Weekdays::WEEKEND));
+        assert!(Weekdays::from_str("someday").is_err());
+        assert!(Weekdays::from_cron("5-1").is_err());
+
+        assert!(CleanTimer::new(9, 0, Weekdays::ALL).validate().is_ok());
+        assert!(CleanTimer::new(24, 0, Weekdays::ALL).validate().is_err());
+        assert!(CleanTimer::new(9, 0, Weekdays(0)).validate().is_err());
+    }
+}
@@ -0,0 +1,196 @@
+//! The error of the vacuum robot, as reported in the `error_code` field of the `get_status` response.
+
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VacuumError {
+    NoError,
+    LaserSensorFault,
+    CollisionSensorFault,
+    WheelsSuspended,
+    CliffSensorFault,
+    MainBrushJammed,
+    SideBrushJammed,
+    WheelsJammed,
+    Stuck,
+    DustbinMissing,
+    FilterBlocked,
+    MagneticStrip,
+    LowBattery,
+    ChargingProblem,
+    BatteryFailure,
+    WallSensorDirty,
+    UnevenSurface,
+    SideBrushFailure,
+    SuctionFanFailure,
+    UnpoweredDock,
+    LaserSensorBlocked,
+    DockContactsDirty,
+    DockUnreachable,
+    NoGoZoneDetected,
+    WallFollowSensorDirty,
+    VibraRiseJammed,
+    OnCarpet,
+    /// An error code which is not known to this library
+    Unknown(i32),
+}
+
+impl VacuumError {
+    /// The numeric error code used by the device
+    pub fn code(&self) -> i32 {
+        match *self {
+            VacuumError::NoError => 0,
+            VacuumError::LaserSensorFault => 1,
+            VacuumError::CollisionSensorFault => 2,
+            VacuumError::WheelsSuspended => 3,
+            VacuumError::CliffSensorFault => 4,
+            VacuumError::MainBrushJammed => 5,
+            VacuumError::SideBrushJammed => 6,
+            VacuumError::WheelsJammed => 7,
+            VacuumError::Stuck => 8,
+            VacuumError::DustbinMissing => 9,
+            VacuumError::FilterBlocked => 10,
+            VacuumError::MagneticStrip => 11,
+            VacuumError::LowBattery => 12,
+            VacuumError::ChargingProblem => 13,
+            VacuumError::BatteryFailure => 14,
+            VacuumError::WallSensorDirty => 15,
+            VacuumError::UnevenSurface => 16,
+            VacuumError::SideBrushFailure => 17,
+            VacuumError::SuctionFanFailure => 18,
+            VacuumError::UnpoweredDock => 19,
+            VacuumError::LaserSensorBlocked => 21,
+            VacuumError::DockContactsDirty => 22,
+            VacuumError::DockUnreachable => 23,
+            VacuumError::NoGoZoneDetected => 24,
+            VacuumError::WallFollowSensorDirty => 26,
+            VacuumError::VibraRiseJammed => 27,
+            VacuumError::OnCarpet => 28,
+            VacuumError::Unknown(code) => code,
+        }
+    }
+
+    /// Check if the robot reports an error at all
+    pub fn is_error(&self) -> bool {
+        *self != VacuumError::NoError
+    }
+
+    /// A short description of the error
+    pub fn description(&self) -> &'static str {
+        match *self {
+            VacuumError::NoError => "No error",
+            VacuumError::LaserSensorFault => "Laser distance sensor error",
+            VacuumError::CollisionSensorFault => "Collision sensor error",
+            VacuumError::WheelsSuspended => "Wheels suspended",
+            VacuumError::CliffSensorFault => "Cliff sensor error",
+            VacuumError::MainBrushJammed => "Main brush jammed",
+            VacuumError::SideBrushJammed => "Side brush jammed",
+            VacuumError::WheelsJammed => "Wheels jammed",
+            VacuumError::Stuck => "Robot stuck",
+            VacuumError::DustbinMissing => "Dustbin missing",
+            VacuumError::FilterBlocked => "Filter blocked",
+            VacuumError::MagneticStrip => "Magnetic strip detected",
+            VacuumError::LowBattery => "Low battery",
+            VacuumError::ChargingProblem => "Charging problem",
+            VacuumError::BatteryFailure => "Battery failure",
+            VacuumError::WallSensorDirty => "Wall sensor dirty",
+            VacuumError::UnevenSurface => "Uneven surface",
+            VacuumError::SideBrushFailure => "Side brush failure",
+            VacuumError::SuctionFanFailure => "Suction fan failure",
+            VacuumError::UnpoweredDock => "Charging dock has no power",
+            VacuumError::LaserSensorBlocked => "Laser distance sensor blocked",
+            VacuumError::DockContactsDirty => "Charging contacts dirty",
+            VacuumError::DockUnreachable => "Charging dock unreachable",
+            VacuumError::NoGoZoneDetected => "No-go zone or virtual wall detected",
+            VacuumError::WallFollowSensorDirty => "Wall following sensor dirty",
+            VacuumError::VibraRiseJammed => "VibraRise system jammed",
+            VacuumError::OnCarpet => "Robot is on carpet",
+            VacuumError::Unknown(_) => "Unknown error",
+        }
+    }
+
+    /// A suggestion for how to fix the error
+    pub fn remedy(&self) -> &'static str {
+        match *self {
+            VacuumError::NoError => "",
+            VacuumError::LaserSensorFault => "Check that the laser distance sensor turret is not blocked and can \
+                                              rotate freely.",
+            VacuumError::CollisionSensorFault => "Tap the front bumper to free it, and remove any debris stuck \
+                                                  around it.",
+            VacuumError::WheelsSuspended => "Move the robot to a new position on the floor and restart.",
+            VacuumError::CliffSensorFault => "Wipe the cliff sensors on the bottom of the robot, then move it \
+                                              away from edges and restart.",
+            VacuumError::MainBrushJammed => "Remove the main brush, clean it and its bearings, then reinstall it.",
+            VacuumError::SideBrushJammed => "Remove the side brush and untangle any hair or debris.",
+            VacuumError::WheelsJammed => "Check the main wheels for entangled objects, then move the robot to a \
+                                          new position and restart.",
+            VacuumError::Stuck => "Clear any obstacles around the robot, then move it to a new position and \
+                                   restart.",
+            VacuumError::DustbinMissing => "Insert the dustbin and filter.",
+            VacuumError::FilterBlocked => "Clean or dry the filter, or replace it.",
+            VacuumError::MagneticStrip => "Move the robot away from the magnetic barrier tape and restart.",
+            VacuumError::LowBattery => "Put the robot back on the charging dock.",
+            VacuumError::ChargingProblem => "Wipe the charging contacts of the robot and the dock, and check that \
+                                             the dock is powered.",
+            VacuumError::BatteryFailure => "Let the battery reach room temperature; replace it if the error \
+                                            persists.",
+            VacuumError::WallSensorDirty => "Wipe the wall distance sensor.",
+            VacuumError::UnevenSurface => "Place the robot on a flat surface and restart.",
+            VacuumError::SideBrushFailure => "Remove and reinstall the side brush, then restart the robot.",
+            VacuumError::SuctionFanFailure => "Clean the filter and dustbin, then restart the robot.",
+            VacuumError::UnpoweredDock => "Check that the charging dock is connected to power.",
+            VacuumError::LaserSensorBlocked => "Remove any objects covering the laser distance sensor.",
+            VacuumError::DockContactsDirty => "Wipe the charging contacts of the dock.",
+            VacuumError::DockUnreachable => "Clear the path to the charging dock, or move the robot onto it.",
+            VacuumError::NoGoZoneDetected => "Move the robot out of the no-go zone or away from the virtual wall.",
+            VacuumError::WallFollowSensorDirty => "Wipe the wall following sensor.",
+            VacuumError::VibraRiseJammed => "Remove any debris from the VibraRise mop module.",
+            VacuumError::OnCarpet => "Move the robot off the carpet, or remove the mop.",
+            VacuumError::Unknown(_) => "Restart the robot; contact support if the error persists.",
+        }
+    }
+}
+
+impl From<i32> for VacuumError {
+    fn from(code: i32) -> VacuumError {
+        match code {
+            0 => VacuumError::NoError,
+            1 => VacuumError::LaserSensorFault,
+            2 => VacuumError::CollisionSensorFault,
+            3 => VacuumError::WheelsSuspended,
+            4 => VacuumError::CliffSensorFault,
+            5 => VacuumError::MainBrushJammed,
+            6 => VacuumError::SideBrushJammed,
+            7 => VacuumError::WheelsJammed,
+            8 => VacuumError::Stuck,
+            9 => VacuumError::DustbinMissing,
+            10 => VacuumError::FilterBlocked,
+            11 => VacuumError::MagneticStrip,
+            12 => VacuumError::LowBattery,
+            13 => VacuumError::ChargingProblem,
+            14 => VacuumError::BatteryFailure,
+            15 => VacuumError::WallSensorDirty,
+            16 => VacuumError::UnevenSurface,
+            17 => VacuumError::SideBrushFailure,
+            18 => VacuumError::SuctionFanFailure,
+            19 => VacuumError::UnpoweredDock,
+            21 => VacuumError::LaserSensorBlocked,
+            22 => VacuumError::DockContactsDirty,
+            23 => VacuumError::DockUnreachable,
+            24 => VacuumError::NoGoZoneDetected,
+            26 => VacuumError::WallFollowSensorDirty,
+            27 => VacuumError::VibraRiseJammed,
+            28 => VacuumError::OnCarpet,
+            code => VacuumError::Unknown(code),
+        }
+    }
+}
+
+impl fmt::Display for VacuumError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            VacuumError::Unknown(code) => f.write_fmt(format_args!("Unknown error ({})", code)),
+            _ => f.write_str(self.description()),
+        }
+    }
+}
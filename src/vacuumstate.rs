@@ -0,0 +1,126 @@
+//! The state of the vacuum robot, as reported in the `state` field of the `get_status` response.
+
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VacuumState {
+    Starting,
+    ChargerDisconnected,
+    Idle,
+    RemoteControl,
+    Cleaning,
+    Returning,
+    Manual,
+    Charging,
+    ChargingProblem,
+    Paused,
+    SpotCleaning,
+    Error,
+    ShuttingDown,
+    Updating,
+    Docking,
+    GoingToTarget,
+    ZonedCleaning,
+    SegmentCleaning,
+    EmptyingBin,
+    WashingMop,
+    GoingToWashMop,
+    ChargingComplete,
+    Offline,
+    /// A state code which is not known to this library
+    Unknown(i32),
+}
+
+impl VacuumState {
+    /// The numeric state code used by the device
+    pub fn code(&self) -> i32 {
+        match *self {
+            VacuumState::Starting => 1,
+            VacuumState::ChargerDisconnected => 2,
+            VacuumState::Idle => 3,
+            VacuumState::RemoteControl => 4,
+            VacuumState::Cleaning => 5,
+            VacuumState::Returning => 6,
+            VacuumState::Manual => 7,
+            VacuumState::Charging => 8,
+            VacuumState::ChargingProblem => 9,
+            VacuumState::Paused => 10,
+            VacuumState::SpotCleaning => 11,
+            VacuumState::Error => 12,
+            VacuumState::ShuttingDown => 13,
+            VacuumState::Updating => 14,
+            VacuumState::Docking => 15,
+            VacuumState::GoingToTarget => 16,
+            VacuumState::ZonedCleaning => 17,
+            VacuumState::SegmentCleaning => 18,
+            VacuumState::EmptyingBin => 22,
+            VacuumState::WashingMop => 23,
+            VacuumState::GoingToWashMop => 26,
+            VacuumState::ChargingComplete => 100,
+            VacuumState::Offline => 101,
+            VacuumState::Unknown(code) => code,
+        }
+    }
+}
+
+impl From<i32> for VacuumState {
+    fn from(code: i32) -> VacuumState {
+        match code {
+            1 => VacuumState::Starting,
+            2 => VacuumState::ChargerDisconnected,
+            3 => VacuumState::Idle,
+            4 => VacuumState::RemoteControl,
+            5 => VacuumState::Cleaning,
+            6 => VacuumState::Returning,
+            7 => VacuumState::Manual,
+            8 => VacuumState::Charging,
+            9 => VacuumState::ChargingProblem,
+            10 => VacuumState::Paused,
+            11 => VacuumState::SpotCleaning,
+            12 => VacuumState::Error,
+            13 => VacuumState::ShuttingDown,
+            14 => VacuumState::Updating,
+            15 => VacuumState::Docking,
+            16 => VacuumState::GoingToTarget,
+            17 => VacuumState::ZonedCleaning,
+            18 => VacuumState::SegmentCleaning,
+            22 => VacuumState::EmptyingBin,
+            23 => VacuumState::WashingMop,
+            26 => VacuumState::GoingToWashMop,
+            100 => VacuumState::ChargingComplete,
+            101 => VacuumState::Offline,
+            code => VacuumState::Unknown(code),
+        }
+    }
+}
+
+impl fmt::Display for VacuumState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            VacuumState::Starting => f.write_str("Starting"),
+            VacuumState::ChargerDisconnected => f.write_str("Charger disconnected"),
+            VacuumState::Idle => f.write_str("Idle"),
+            VacuumState::RemoteControl => f.write_str("Remote control active"),
+            VacuumState::Cleaning => f.write_str("Cleaning"),
+            VacuumState::Returning => f.write_str("Returning home"),
+            VacuumState::Manual => f.write_str("Manual mode"),
+            VacuumState::Charging => f.write_str("Charging"),
+            VacuumState::ChargingProblem => f.write_str("Charging problem"),
+            VacuumState::Paused => f.write_str("Paused"),
+            VacuumState::SpotCleaning => f.write_str("Spot cleaning"),
+            VacuumState::Error => f.write_str("In error"),
+            VacuumState::ShuttingDown => f.write_str("Shutting down"),
+            VacuumState::Updating => f.write_str("Updating"),
+            VacuumState::Docking => f.write_str("Docking"),
+            VacuumState::GoingToTarget => f.write_str("Going to target"),
+            VacuumState::ZonedCleaning => f.write_str("Zoned cleaning"),
+            VacuumState::SegmentCleaning => f.write_str("Segment cleaning"),
+            VacuumState::EmptyingBin => f.write_str("Emptying the bin"),
+            VacuumState::WashingMop => f.write_str("Washing the mop"),
+            VacuumState::GoingToWashMop => f.write_str("Going to wash the mop"),
+            VacuumState::ChargingComplete => f.write_str("Charging complete"),
+            VacuumState::Offline => f.write_str("Device offline"),
+            VacuumState::Unknown(code) => f.write_fmt(format_args!("Unknown state ({})", code)),
+        }
+    }
+}
@@ -0,0 +1,155 @@
+//! Rectangular zones for the `app_zoned_clean` method.
+//!
+//! Zones are given in the robot's coordinate space, in millimeters. The map spans `0` to `51200` on both axes, and
+//! the charging dock is usually located at (or near) the origin of a new map, `(25500, 25500)`.
+
+use serde::{Serialize, Deserialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+/// The largest coordinate of the robot's coordinate space
+pub const MAX_COORDINATE: u32 = 51200;
+
+/// The maximum number of zones which can be cleaned with a single `app_zoned_clean` request
+pub const MAX_ZONES: usize = 5;
+
+/// The maximum number of times a zone can be cleaned in a single request
+pub const MAX_REPEATS: u32 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Zone {
+    pub x1: u32,
+    pub y1: u32,
+    pub x2: u32,
+    pub y2: u32,
+    #[serde(default = "default_repeats")]
+    pub repeats: u32,
+}
+
+impl Zone {
+    /// Create a zone from two opposite corners, in any order
+    pub fn new(xa: u32, ya: u32, xb: u32, yb: u32, repeats: u32) -> Zone {
+        Zone {
+            x1: xa.min(xb),
+            y1: ya.min(yb),
+            x2: xa.max(xb),
+            y2: ya.max(yb),
+            repeats
+        }
+    }
+
+    /// Check that the zone is a non-empty rectangle inside the robot's coordinate space, with a valid repeat count
+    pub fn validate(&self) -> Result<(), String> {
+        if self.x1 >= self.x2 || self.y1 >= self.y2 {
+            return Err(format!("zone {:?} is empty, or its corners are not ordered", self));
+        }
+        if self.x2 > MAX_COORDINATE || self.y2 > MAX_COORDINATE {
+            return Err(format!("zone {:?} is outside of the map (0..{})", self, MAX_COORDINATE));
+        }
+        if self.repeats < 1 || self.repeats > MAX_REPEATS {
+            return Err(format!("zone {:?} repeat count must be between 1 and {}", self, MAX_REPEATS));
+        }
+        Ok(())
+    }
+
+    /// The zone, as used in the parameters of the `app_zoned_clean` method
+    pub fn to_params(&self) -> [u32; 5] {
+        [self.x1, self.y1, self.x2, self.y2, self.repeats]
+    }
+}
+
+/// Check that a list of zones can be sent in a single `app_zoned_clean` request
+pub fn validate_zones(zones: &[Zone]) -> Result<(), String> {
+    if zones.is_empty() {
+        return Err("no zones given".to_string());
+    }
+    if zones.len() > MAX_ZONES {
+        return Err(format!("{} zones given, at most {} are supported", zones.len(), MAX_ZONES));
+    }
+    for zone in zones {
+        zone.validate()?;
+    }
+    Ok(())
+}
+
+/// Load named zones from a JSON file, e.g.
+///
+/// ```json
+/// {
+///     "kitchen": [{"x1": 25000, "y1": 22000, "x2": 28500, "y2": 25000, "repeats": 2}],
+///     "hallway": [{"x1": 23000, "y1": 25500, "x2": 24500, "y2": 31000}]
+/// }
+/// ```
+///
+/// # Arguments
+///
+/// `path` - the named zones file
+///
+pub fn load_named_zones(path: &Path) -> Result<BTreeMap<String, Vec<Zone>>, String> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) => { return Err(format!("{}: {}", path.display(), e)); }
+    };
+    match serde_json::from_str(&content) {
+        Ok(zones) => Ok(zones),
+        Err(e) => Err(format!("{}: {}", path.display(), e))
+    }
+}
+
+fn default_repeats() -> u32 {
+    1
+}
+
+impl FromStr for Zone {
+    type Err = String;
+
+    /// Parse a zone from `x1,y1,x2,y2` or `x1,y1,x2,y2,repeats`
+    fn from_str(s: &str) -> Result<Zone, String> {
+        let values: Result<Vec<u32>, _> = s.split(',').map(|v| v.trim().parse::<u32>()).collect();
+        match values {
+            Ok(v) if v.len() == 4 => Ok(Zone::new(v[0], v[1], v[2], v[3], default_repeats())),
+            Ok(v) if v.len() == 5 => Ok(Zone::new(v[0], v[1], v[2], v[3], v[4])),
+            _ => Err(s.to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zone_from_str() {
+        assert_eq!(Zone::from_str("25000,22000,28500,25000"), Ok(Zone::new(25000, 22000, 28500, 25000, 1)));
+        assert_eq!(Zone::from_str("28500, 25000, 25000, 22000, 2"),
+                   Ok(Zone { x1: 25000, y1: 22000, x2: 28500, y2: 25000, repeats: 2 }));
+        assert!(Zone::from_str("25000,22000,28500").is_err());
+        assert!(Zone::from_str("a,b,c,d").is_err());
+    }
+
+    #[test]
+    fn test_zone_validate() {
+        assert!(Zone::new(25000, 22000, 28500, 25000, 1).validate().is_ok());
+        assert!(Zone::new(25000, 22000, 25000, 25000, 1).validate().is_err());
+        assert!(Zone::new(25000, 22000, 52000, 25000, 1).validate().is_err());
+        assert!(Zone::new(25000, 22000, 28500, 25000, 0).validate().is_err());
+        assert!(Zone::new(25000, 22000, 28500, 25000, 4).validate().is_err());
+        assert!(Zone { x1: 28500, y1: 22000, x2: 25000, y2: 25000, repeats: 1 }.validate().is_err());
+
+        let zone = Zone::new(25000, 22000, 28500, 25000, 1);
+        assert!(validate_zones(&[zone; 5]).is_ok());
+        assert!(validate_zones(&[zone; 6]).is_err());
+        assert!(validate_zones(&[]).is_err());
+    }
+
+    #[test]
+    fn test_named_zones() {
+        let zones: BTreeMap<String, Vec<Zone>> = serde_json::from_str(
+            "{\"kitchen\": [{\"x1\": 25000, \"y1\": 22000, \"x2\": 28500, \"y2\": 25000, \"repeats\": 2}],
+              \"hallway\": [{\"x1\": 23000, \"y1\": 25500, \"x2\": 24500, \"y2\": 31000}]}").unwrap();
+        assert_eq!(zones["kitchen"], vec!(Zone::new(25000, 22000, 28500, 25000, 2)));
+        assert_eq!(zones["hallway"][0].repeats, 1);
+    }
+}